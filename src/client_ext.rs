@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 
 use bitcoin::{psbt::PartiallySignedTransaction, Address, Network, Txid};
 use bitcoind::bitcoincore_rpc::{
@@ -21,10 +21,19 @@ pub trait ClientExt {
         internal: bool,
     ) -> Result<ImportMultiResult, Error>;
 
+    /// Import the active descriptors, given with their `internal` flag, in a single call, so
+    /// that a rescan from `timestamp`, if in the past, is done once
+    fn import_active_descriptors(
+        &self,
+        descriptors: &[(&str, bool)],
+        timestamp: Timestamp,
+    ) -> Result<Vec<ImportMultiResult>, Error>;
+
     fn get_new_bech32m_address(&self, network: Network) -> Result<Address, Error>;
     fn send_all(&self, rec: &Address) -> Txid;
-    fn wallet_passphrase(&self, passphrase: &str);
-    fn wallet_lock(&self);
+    /// Unlock the wallet for `timeout`, it must cover the calls needing the private keys
+    fn wallet_passphrase(&self, passphrase: &str, timeout: Duration) -> Result<(), Error>;
+    fn wallet_lock(&self) -> Result<(), Error>;
 
     fn create_blank_wallet(
         &self,
@@ -70,6 +79,27 @@ impl ClientExt for Client {
         Ok(vec.pop().unwrap())
     }
 
+    fn import_active_descriptors(
+        &self,
+        descriptors: &[(&str, bool)],
+        timestamp: Timestamp,
+    ) -> Result<Vec<ImportMultiResult>, Error> {
+        let requests: Vec<_> = descriptors
+            .iter()
+            .map(|(descriptor, internal)| ImportDescriptors {
+                descriptor: descriptor.to_string(),
+                timestamp,
+                active: Some(true),
+                range: None,
+                next_index: None,
+                internal: Some(*internal),
+                label: None,
+            })
+            .collect();
+        let args = [to_value(requests)?];
+        self.call("importdescriptors", &args)
+    }
+
     fn get_new_bech32m_address(&self, network: Network) -> Result<Address, Error> {
         let address = self.get_new_address(None, Some(AddressType::Bech32m))?;
         address
@@ -85,16 +115,17 @@ impl ClientExt for Client {
         Txid::from_str(result.get("txid").unwrap().as_str().unwrap()).unwrap()
     }
 
-    fn wallet_passphrase(&self, passphrase: &str) {
-        let val: Value = self
-            .call("walletpassphrase", &[passphrase.into(), 10.into()])
-            .unwrap();
-        eprintln!("{val:?}");
+    fn wallet_passphrase(&self, passphrase: &str, timeout: Duration) -> Result<(), Error> {
+        let _: Value = self.call(
+            "walletpassphrase",
+            &[passphrase.into(), timeout.as_secs().into()],
+        )?;
+        Ok(())
     }
 
-    fn wallet_lock(&self) {
-        let val: Value = self.call("walletlock", &[]).unwrap();
-        eprintln!("{val:?}");
+    fn wallet_lock(&self) -> Result<(), Error> {
+        let _: Value = self.call("walletlock", &[])?;
+        Ok(())
     }
 
     fn create_blank_wallet(
@@ -0,0 +1,305 @@
+use std::{fmt::Write, str::FromStr};
+
+use bitcoin::{
+    base58,
+    bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint},
+    secp256k1::Secp256k1,
+    Network,
+};
+use clap::ValueEnum;
+
+use super::Seed;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AccountXpubError {
+    #[error(transparent)]
+    Bip32(#[from] bitcoin::bip32::Error),
+
+    #[error(transparent)]
+    Base58(#[from] base58::Error),
+}
+
+/// How the extended public key is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum XpubFormat {
+    /// `xpub` on mainnet and `tpub` on test networks, whatever the path
+    #[default]
+    Plain,
+
+    /// SLIP-132 version bytes inferred from the path: `ypub` for bip49, `zpub` for bip84, `Ypub`
+    /// and `Zpub` for bip48 nested and native multisig. Other paths, like bip86, are plain.
+    Slip132,
+}
+
+/// Given a seed and a derivation path returns the extended public key with its key origin, like
+/// `[fingerprint/path]xpub`, so that it could be given as cosigner or heir key.
+///
+/// The path could start with `m/` or not, hardened steps could be marked with `h` or `'`, in the
+/// origin they are always printed with `h`.
+pub fn account_xpub(
+    seed: &Seed,
+    network: Network,
+    path: &str,
+    format: XpubFormat,
+) -> Result<String, AccountXpubError> {
+    let (fingerprint, path, xpub) = derive(seed, network, path)?;
+
+    let xkey = match (format, slip132_version(&path, network)) {
+        (XpubFormat::Slip132, Some(version)) => {
+            let mut data = xpub.encode();
+            data[..4].copy_from_slice(&version);
+            base58::encode_check(&data)
+        }
+        _ => xpub.to_string(),
+    };
+
+    Ok(format!("[{}]{xkey}", origin(fingerprint, &path)))
+}
+
+fn derive(
+    seed: &Seed,
+    network: Network,
+    path: &str,
+) -> Result<(Fingerprint, DerivationPath, ExtendedPubKey), AccountXpubError> {
+    let path = path.trim().trim_start_matches("m/");
+    let path = match path {
+        "m" | "" => DerivationPath::master(),
+        _ => DerivationPath::from_str(&format!("m/{path}"))?,
+    };
+
+    let fingerprint = seed.fingerprint()?;
+
+    let secp = Secp256k1::new();
+    let xprv = seed.xprv(network)?.derive_priv(&secp, &path)?;
+    let xpub = ExtendedPubKey::from_priv(&secp, &xprv);
+
+    Ok((fingerprint, path, xpub))
+}
+
+/// Encode the extended public key derived from the seed at the given path as an uppercase
+/// `UR:CRYPTO-HDKEY/...` (BCR-2020-007), which uses only characters of the QR alphanumeric mode and
+/// is understood by other wallets, like Sparrow, scanning QR codes.
+///
+/// The key origin is included, the SLIP-132 version is not part of the format.
+pub fn account_xpub_ur(
+    seed: &Seed,
+    network: Network,
+    path: &str,
+) -> Result<String, AccountXpubError> {
+    let (fingerprint, path, xpub) = derive(seed, network, path)?;
+
+    let mut keypath = vec![];
+    cbor_head(&mut keypath, MAJOR_MAP, 3);
+    cbor_head(&mut keypath, MAJOR_UNSIGNED, 1);
+    cbor_head(&mut keypath, MAJOR_ARRAY, 2 * path.len() as u64);
+    for child in path.into_iter() {
+        let (index, hardened) = match child {
+            ChildNumber::Hardened { index } => (index, true),
+            ChildNumber::Normal { index } => (index, false),
+        };
+        cbor_head(&mut keypath, MAJOR_UNSIGNED, *index as u64);
+        keypath.push(if hardened { CBOR_TRUE } else { CBOR_FALSE });
+    }
+    cbor_head(&mut keypath, MAJOR_UNSIGNED, 2);
+    cbor_head(&mut keypath, MAJOR_UNSIGNED, fingerprint_u32(fingerprint));
+    cbor_head(&mut keypath, MAJOR_UNSIGNED, 3);
+    cbor_head(&mut keypath, MAJOR_UNSIGNED, path.len() as u64);
+
+    let mut data = vec![];
+    let entries = if path.is_empty() { 4 } else { 5 };
+    cbor_head(&mut data, MAJOR_MAP, entries);
+    cbor_head(&mut data, MAJOR_UNSIGNED, 3);
+    cbor_head(&mut data, MAJOR_BYTES, 33);
+    data.extend(xpub.public_key.serialize());
+    cbor_head(&mut data, MAJOR_UNSIGNED, 4);
+    cbor_head(&mut data, MAJOR_BYTES, 32);
+    data.extend(xpub.chain_code.as_bytes());
+    cbor_head(&mut data, MAJOR_UNSIGNED, 5);
+    cbor_head(&mut data, MAJOR_TAG, TAG_COIN_INFO);
+    cbor_head(&mut data, MAJOR_MAP, 1);
+    cbor_head(&mut data, MAJOR_UNSIGNED, 2);
+    let testnet = network != Network::Bitcoin;
+    cbor_head(&mut data, MAJOR_UNSIGNED, testnet as u64);
+    cbor_head(&mut data, MAJOR_UNSIGNED, 6);
+    cbor_head(&mut data, MAJOR_TAG, TAG_KEYPATH);
+    data.extend(keypath);
+    if !path.is_empty() {
+        cbor_head(&mut data, MAJOR_UNSIGNED, 8);
+        let parent = fingerprint_u32(xpub.parent_fingerprint);
+        cbor_head(&mut data, MAJOR_UNSIGNED, parent);
+    }
+
+    Ok(format!("ur:crypto-hdkey/{}", bytewords(&data)).to_ascii_uppercase())
+}
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+const CBOR_FALSE: u8 = 0xf4;
+const CBOR_TRUE: u8 = 0xf5;
+const TAG_KEYPATH: u64 = 304;
+const TAG_COIN_INFO: u64 = 305;
+
+/// The 256 bytewords (BCR-2020-012), in the minimal encoding a byte is the first and last letter
+const BYTEWORDS: &str = "able acid also apex aqua arch atom aunt away axis back bald barn belt \
+    beta bias blue body brag brew bulb buzz calm cash cats chef city claw code cola cook cost crux \
+    curl cusp cyan dark data days deli dice diet door down draw drop drum dull duty each easy echo \
+    edge epic even exam exit eyes fact fair fern figs film fish fizz flap flew flux foxy free frog \
+    fuel fund gala game gear gems gift girl glow good gray grim guru gush gyro half hang hard hawk \
+    heat help high hill holy hope horn huts iced idea idle inch inky into iris iron item jade jazz \
+    join jolt jowl judo jugs jump junk jury keep keno kept keys kick kiln king kite kiwi knob lamb \
+    lava lazy leaf legs liar limp lion list logo loud love luau luck lung main many math maze memo \
+    menu meow mild mint miss monk nail navy need news next noon note numb obey oboe omit onyx open \
+    oval owls paid part peck play plus poem pool pose puff puma purr quad quiz race ramp real redo \
+    rich road rock roof ruby ruin runs rust safe saga scar sets silk skew slot soap solo song stub \
+    surf swan taco task taxi tent tied time tiny toil tomb toys trip tuna twin ugly undo unit urge \
+    user vast very veto vial vibe view visa void vows wall wand warm wasp wave waxy webs what when \
+    whiz wolf work yank yawn yell yoga yurt zaps zero zest zinc zone zoom";
+
+/// Append the head of a CBOR item of the given major type and argument
+fn cbor_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend([major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend((value as u16).to_be_bytes());
+        }
+        0x10000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend((value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend(value.to_be_bytes());
+        }
+    }
+}
+
+/// Minimal bytewords encoding of the data followed by its CRC32 checksum
+fn bytewords(data: &[u8]) -> String {
+    let words: Vec<_> = BYTEWORDS.split_whitespace().collect();
+    data.iter()
+        .chain(crc32(data).to_be_bytes().iter())
+        .flat_map(|b| {
+            let word = words[*b as usize];
+            [&word[..1], &word[3..]]
+        })
+        .collect()
+}
+
+/// CRC32 with the ISO-HDLC polynomial, as used by zlib
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn fingerprint_u32(fingerprint: Fingerprint) -> u64 {
+    u32::from_be_bytes(fingerprint.to_bytes()) as u64
+}
+
+/// The key origin without brackets, like `fingerprint/86h/0h/0h`, hardened steps marked with `h`
+pub(crate) fn origin(fingerprint: Fingerprint, path: &DerivationPath) -> String {
+    let mut origin = fingerprint.to_string();
+    for child in path.into_iter() {
+        match child {
+            ChildNumber::Hardened { index } => write!(origin, "/{index}h"),
+            ChildNumber::Normal { index } => write!(origin, "/{index}"),
+        }
+        .expect("write to string doesn't fail");
+    }
+    origin
+}
+
+/// SLIP-132 version bytes of the extended public key for the purpose of the path, if any
+fn slip132_version(path: &DerivationPath, network: Network) -> Option<[u8; 4]> {
+    let mainnet = network == Network::Bitcoin;
+    let hardened = |index| ChildNumber::Hardened { index };
+    let version: u32 = match (path.as_ref().first(), path.as_ref().get(3), mainnet) {
+        (Some(p), _, true) if *p == hardened(49) => 0x049d7cb2,
+        (Some(p), _, false) if *p == hardened(49) => 0x044a5262,
+        (Some(p), _, true) if *p == hardened(84) => 0x04b24746,
+        (Some(p), _, false) if *p == hardened(84) => 0x045f1cf6,
+        (Some(p), Some(s), true) if *p == hardened(48) && *s == hardened(1) => 0x0295b43f,
+        (Some(p), Some(s), false) if *p == hardened(48) && *s == hardened(1) => 0x024289ef,
+        (Some(p), Some(s), true) if *p == hardened(48) && *s == hardened(2) => 0x02aa7ed3,
+        (Some(p), Some(s), false) if *p == hardened(48) && *s == hardened(2) => 0x02575483,
+        _ => return None,
+    };
+    Some(version.to_be_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use bitcoin::Network;
+
+    use super::{account_xpub, account_xpub_ur, XpubFormat};
+    use crate::commands::Seed;
+
+    const MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_account_xpub() {
+        let seed = Seed::from_str(MNEMONIC).unwrap();
+        let xpub = |path, format| account_xpub(&seed, Network::Bitcoin, path, format).unwrap();
+
+        // bip84 test vector
+        assert_eq!(
+            xpub("m/84'/0'/0'", XpubFormat::Slip132),
+            "[73c5da0a/84h/0h/0h]zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs"
+        );
+        assert_eq!(
+            xpub("84h/0h/0h", XpubFormat::Plain),
+            "[73c5da0a/84h/0h/0h]xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V"
+        );
+
+        // bip49 test vector
+        assert_eq!(
+            xpub("m/49h/0h/0h", XpubFormat::Slip132),
+            "[73c5da0a/49h/0h/0h]ypub6Ww3ibxVfGzLrAH1PNcjyAWenMTbbAosGNB6VvmSEgytSER9azLDWCxoJwW7Ke7icmizBMXrzBx9979FfaHxHcrArf3zbeJJJUZPf663zsP"
+        );
+
+        assert!(
+            xpub("m/48h/0h/0h/2h", XpubFormat::Slip132).starts_with("[73c5da0a/48h/0h/0h/2h]Zpub")
+        );
+        assert!(xpub("m/86h/0h/0h", XpubFormat::Slip132).starts_with("[73c5da0a/86h/0h/0h]xpub"));
+        assert!(xpub("m/0/1", XpubFormat::Plain).starts_with("[73c5da0a/0/1]xpub"));
+
+        let testnet = account_xpub(&seed, Network::Testnet, "84h/1h/0h", XpubFormat::Slip132);
+        assert!(testnet.unwrap().starts_with("[73c5da0a/84h/1h/0h]vpub"));
+
+        assert!(account_xpub(&seed, Network::Bitcoin, "84x/0h", XpubFormat::Plain).is_err());
+    }
+
+    #[test]
+    fn test_account_xpub_ur() {
+        assert_eq!(super::crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(
+            super::bytewords(&[0, 1, 2, 0x80, 0xff]),
+            "aeadaolazmjendeoti"
+        );
+
+        let seed = Seed::from_str(MNEMONIC).unwrap();
+        let ur = account_xpub_ur(&seed, Network::Bitcoin, "m/84h/0h/0h").unwrap();
+        assert_eq!(ur, "UR:CRYPTO-HDKEY/ONAXHDCLAOJOKNIDZCPSSAJTPTRPFRCECFKKAMYKJTVTCSBTBDTKCFIYVYOETNEEYKWFNBNYNDAAHDCXGEGUNBPYCLRHUOMDLNNSGLMOOYHSCFGLAXRTWSFHYKADGESWMOWKEOSSKOGHMHZTAHTAADEHOYAOAEAMTAADDYOTADLNCSGHYKAEYKAEYKAOCYJKSKTNBKAXAXAYCYKBWFDNUYYLLTLYBE");
+
+        let master = account_xpub_ur(&seed, Network::Testnet, "m").unwrap();
+        assert!(master.starts_with("UR:CRYPTO-HDKEY/OXAXHDCLA"), "{master}");
+        assert_ne!(
+            ur,
+            account_xpub_ur(&seed, Network::Testnet, "m/84h/0h/0h").unwrap()
+        );
+    }
+}
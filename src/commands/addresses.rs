@@ -0,0 +1,93 @@
+use bitcoin::Network;
+use miniscript::ForEachKey;
+
+use super::{account_xpub::origin, import::single_descriptors, ImportError};
+use crate::Descriptor;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AddressesError {
+    #[error(transparent)]
+    Miniscript(#[from] miniscript::Error),
+
+    #[error(transparent)]
+    Conversion(#[from] miniscript::descriptor::ConversionError),
+
+    #[error(transparent)]
+    Import(#[from] ImportError),
+
+    #[error("The descriptor has no change branch, it must be multipath like `/<0;1>/*`")]
+    MissingChange,
+}
+
+/// Derive `count` addresses starting at index `start` from the receive branch of the multipath
+/// descriptor, or from the change branch if `change`. No node is needed.
+///
+/// Every line contains the address followed by the key origins of the derived keys, like
+/// `bc1p... [fingerprint/86h/0h/0h/0/5]`, so that it could be compared with other devices.
+pub fn addresses(
+    descriptor: &Descriptor,
+    network: Network,
+    change: bool,
+    start: u32,
+    count: u32,
+) -> Result<String, AddressesError> {
+    let singles = single_descriptors(descriptor)?;
+    let single = singles
+        .get(usize::from(change))
+        .ok_or(AddressesError::MissingChange)?;
+
+    let mut lines = vec![];
+    for index in start..start.saturating_add(count) {
+        let derived = single.at_derivation_index(index)?;
+        let address = derived.address(network)?;
+        let mut origins = vec![];
+        derived.for_each_key(|key| {
+            if let Some(path) = key.full_derivation_path() {
+                origins.push(format!("[{}]", origin(key.master_fingerprint(), &path)));
+            }
+            true
+        });
+        lines.push(format!("{address} {}", origins.join(" ")));
+    }
+
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use bitcoin::Network;
+
+    use super::{addresses, AddressesError};
+    use crate::Descriptor;
+
+    #[test]
+    fn test_addresses() {
+        // bip86 test vector
+        let desc = "tr([73c5da0a/86h/0h/0h]xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ/<0;1>/*)";
+        let desc = Descriptor::from_str(desc).unwrap();
+
+        let receive = addresses(&desc, Network::Bitcoin, false, 0, 2).unwrap();
+        assert_eq!(
+            receive,
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr [73c5da0a/86h/0h/0h/0/0]\n\
+             bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh [73c5da0a/86h/0h/0h/0/1]"
+        );
+
+        let change = addresses(&desc, Network::Bitcoin, true, 0, 1).unwrap();
+        assert_eq!(
+            change,
+            "bc1p3qkhfews2uk44qtvauqyr2ttdsw7svhkl9nkm9s9c3x4ax5h60wqwruhk7 [73c5da0a/86h/0h/0h/1/0]"
+        );
+
+        let skipped = addresses(&desc, Network::Bitcoin, false, 1, 1).unwrap();
+        assert_eq!(receive.lines().nth(1).unwrap(), skipped);
+
+        let single = Descriptor::from_str("tr([73c5da0a/86h/0h/0h]xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ/0/*)").unwrap();
+        assert!(matches!(
+            addresses(&single, Network::Bitcoin, true, 0, 1),
+            Err(AddressesError::MissingChange)
+        ));
+    }
+}
@@ -0,0 +1,196 @@
+use bip39::Mnemonic;
+use bitcoin::{
+    bip32::{ChainCode, ChildNumber, DerivationPath, ExtendedPrivKey, Fingerprint},
+    hashes::{hmac, sha512, Hash, HashEngine},
+    secp256k1::{Secp256k1, SecretKey},
+    Network, PrivateKey,
+};
+use clap::ValueEnum;
+
+use super::Seed;
+
+const BIP85_PURPOSE: u32 = 83696968;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Bip85Error {
+    #[error(transparent)]
+    Bip32(#[from] bitcoin::bip32::Error),
+
+    #[error(transparent)]
+    Bip39(#[from] bip39::Error),
+
+    #[error(transparent)]
+    Secp256k1(#[from] bitcoin::secp256k1::Error),
+
+    #[error("Invalid number of words {0}, must be 12, 18 or 24")]
+    InvalidWords(u32),
+
+    #[error("Invalid number of bytes {0}, must be between 16 and 64")]
+    InvalidBytes(u32),
+}
+
+/// The kind of child secret derived with bip85
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Bip85Application {
+    /// A bip39 mnemonic of 12, 18 or 24 words
+    Bip39,
+
+    /// A private key in WIF format
+    Wif,
+
+    /// An extended private key
+    Xprv,
+
+    /// Hex encoded entropy, from 16 to 64 bytes
+    Hex,
+}
+
+/// Derive deterministic child entropy from the given seed as specified in bip85
+///
+/// `words` is used only by `Bip39` and `bytes` only by `Hex`
+pub fn bip85(
+    seed: &Seed,
+    network: Network,
+    application: Bip85Application,
+    words: u32,
+    bytes: u32,
+    index: u32,
+) -> Result<String, Bip85Error> {
+    let root = seed.xprv(network)?;
+    derive_application(&root, application, words, bytes, index)
+}
+
+fn derive_application(
+    root: &ExtendedPrivKey,
+    application: Bip85Application,
+    words: u32,
+    bytes: u32,
+    index: u32,
+) -> Result<String, Bip85Error> {
+    let index = ChildNumber::from_hardened_idx(index)?;
+    Ok(match application {
+        Bip85Application::Bip39 => {
+            if !matches!(words, 12 | 18 | 24) {
+                return Err(Bip85Error::InvalidWords(words));
+            }
+            let language = ChildNumber::from_hardened_idx(0)?; // english
+            let path = [hardened(39)?, language, hardened(words)?, index];
+            let entropy = derive_entropy(root, &path)?;
+            let len = (words * 4 / 3) as usize;
+            Mnemonic::from_entropy(&entropy[..len])?.to_string()
+        }
+        Bip85Application::Wif => {
+            let entropy = derive_entropy(root, &[hardened(2)?, index])?;
+            let key = SecretKey::from_slice(&entropy[..32])?;
+            PrivateKey::new(key, root.network).to_wif()
+        }
+        Bip85Application::Xprv => {
+            let entropy = derive_entropy(root, &[hardened(32)?, index])?;
+            let mut chain_code = [0u8; 32];
+            chain_code.copy_from_slice(&entropy[..32]);
+            ExtendedPrivKey {
+                network: root.network,
+                depth: 0,
+                parent_fingerprint: Fingerprint::default(),
+                child_number: ChildNumber::from_normal_idx(0)?,
+                private_key: SecretKey::from_slice(&entropy[32..])?,
+                chain_code: ChainCode::from(chain_code),
+            }
+            .to_string()
+        }
+        Bip85Application::Hex => {
+            if !(16..=64).contains(&bytes) {
+                return Err(Bip85Error::InvalidBytes(bytes));
+            }
+            let entropy = derive_entropy(root, &[hardened(128169)?, hardened(bytes)?, index])?;
+            entropy[..bytes as usize]
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect()
+        }
+    })
+}
+
+fn hardened(index: u32) -> Result<ChildNumber, Bip85Error> {
+    Ok(ChildNumber::from_hardened_idx(index)?)
+}
+
+/// Derive the private key at `m/83696968'/path` and returns `HMAC-SHA512("bip-entropy-from-k", k)`
+fn derive_entropy(root: &ExtendedPrivKey, path: &[ChildNumber]) -> Result<[u8; 64], Bip85Error> {
+    let secp = Secp256k1::new();
+    let mut full_path = vec![hardened(BIP85_PURPOSE)?];
+    full_path.extend_from_slice(path);
+    let derived = root.derive_priv(&secp, &DerivationPath::from(full_path))?;
+
+    let mut engine = hmac::HmacEngine::<sha512::Hash>::new(b"bip-entropy-from-k");
+    engine.input(&derived.private_key.secret_bytes());
+    Ok(hmac::Hmac::<sha512::Hash>::from_engine(engine).to_byte_array())
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use bitcoin::bip32::{ChildNumber, ExtendedPrivKey};
+
+    use super::{derive_application, derive_entropy, Bip85Application, Bip85Error};
+
+    // test vectors from https://github.com/bitcoin/bips/blob/master/bip-0085.mediawiki
+    const ROOT: &str = "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb";
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn test_entropy() {
+        let root = ExtendedPrivKey::from_str(ROOT).unwrap();
+        let path = [
+            ChildNumber::from_hardened_idx(0).unwrap(),
+            ChildNumber::from_hardened_idx(0).unwrap(),
+        ];
+        assert_eq!(hex(&derive_entropy(&root, &path).unwrap()), "efecfbccffea313214232d29e71563d941229afb4338c21f9517c41aaa0d16f00b83d2a09ef747e7a64e8e2bd5a14869e693da66ce94ac2da570ab7ee48618f7");
+    }
+
+    #[test]
+    fn test_applications() {
+        let root = ExtendedPrivKey::from_str(ROOT).unwrap();
+        let derive = |application, words, bytes| {
+            derive_application(&root, application, words, bytes, 0).unwrap()
+        };
+
+        assert_eq!(
+            derive(Bip85Application::Bip39, 12, 0),
+            "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose"
+        );
+        assert_eq!(
+            derive(Bip85Application::Bip39, 18, 0),
+            "near account window bike charge season chef number sketch tomorrow excuse sniff circle vital hockey outdoor supply token"
+        );
+        assert_eq!(
+            derive(Bip85Application::Bip39, 24, 0),
+            "puppy ocean match cereal symbol another shed magic wrap hammer bulb intact gadget divorce twin tonight reason outdoor destroy simple truth cigar social volcano"
+        );
+        assert_eq!(
+            derive(Bip85Application::Wif, 0, 0),
+            "Kzyv4uF39d4Jrw2W7UryTHwZr1zQVNk4dAFyqE6BuMrMh1Za7uhp"
+        );
+        assert_eq!(
+            derive(Bip85Application::Xprv, 0, 0),
+            "xprv9s21ZrQH143K2srSbCSg4m4kLvPMzcWydgmKEnMmoZUurYuBuYG46c6P71UGXMzmriLzCCBvKQWBUv3vPB3m1SATMhp3uEjXHJ42jFg7myX"
+        );
+        assert_eq!(
+            derive(Bip85Application::Hex, 0, 64),
+            "492db4698cf3b73a5a24998aa3e9d7fa96275d85724a91e71aa2d645442f878555d078fd1f1f67e368976f04137b1f7a0d19232136ca50c44614af72b5582a5c"
+        );
+
+        assert!(matches!(
+            derive_application(&root, Bip85Application::Bip39, 15, 0, 0),
+            Err(Bip85Error::InvalidWords(15))
+        ));
+        assert!(matches!(
+            derive_application(&root, Bip85Application::Hex, 0, 65, 0),
+            Err(Bip85Error::InvalidBytes(65))
+        ));
+    }
+}
@@ -0,0 +1,313 @@
+//! Error correction of Codex32 strings with the short checksum.
+//!
+//! The bip93 checksum is a BCH code over GF(32): the roots of the generator are in GF(1024) and
+//! include 8 consecutive powers of an element of order 93, so up to 4 substituted characters can
+//! be located and fixed with the classic syndrome decoding: Berlekamp-Massey to find the error
+//! locator polynomial, Chien search to find the positions and Forney to find the values.
+
+use std::{
+    fmt::Display,
+    ops::{Add, Mul},
+};
+
+use codex32::{ChecksumEngine, Codex32String, Fe};
+
+/// Maximum number of substitutions correctable by the short checksum
+pub const MAX_ERRORS: usize = 4;
+
+/// The residue of a valid short checksum
+const TARGET: &str = "secretshare32";
+
+/// Order of [`BETA`], which is also the maximum length of a short codex32 string
+const N: usize = 93;
+
+/// `BETA^FIRST_ROOT ... BETA^(FIRST_ROOT + 2 * MAX_ERRORS - 1)` are roots of the generator
+const BETA: Gf1024 = Gf1024(Fe::Q, Fe::_9);
+const FIRST_ROOT: usize = 9;
+
+/// An element `a + bζ` of GF(1024) where `ζ² = ζ + 1`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Gf1024(Fe, Fe);
+
+impl Gf1024 {
+    const ZERO: Gf1024 = Gf1024(Fe::Q, Fe::Q);
+    const ONE: Gf1024 = Gf1024(Fe::P, Fe::Q);
+
+    fn pow(self, mut exp: usize) -> Gf1024 {
+        let mut base = self;
+        let mut result = Gf1024::ONE;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exp >>= 1;
+        }
+        result
+    }
+
+    fn inv(self) -> Gf1024 {
+        self.pow(1022)
+    }
+}
+
+impl From<Fe> for Gf1024 {
+    fn from(fe: Fe) -> Self {
+        Gf1024(fe, Fe::Q)
+    }
+}
+
+impl Add for Gf1024 {
+    type Output = Gf1024;
+    fn add(self, other: Gf1024) -> Gf1024 {
+        Gf1024(self.0 + other.0, self.1 + other.1)
+    }
+}
+
+impl Mul for Gf1024 {
+    type Output = Gf1024;
+    fn mul(self, other: Gf1024) -> Gf1024 {
+        let bd = self.1 * other.1;
+        Gf1024(
+            self.0 * other.0 + bd,
+            self.0 * other.1 + self.1 * other.0 + bd,
+        )
+    }
+}
+
+/// Evaluate the polynomial with coefficients from the highest degree to the lowest
+fn eval(poly: &[Gf1024], x: Gf1024) -> Gf1024 {
+    poly.iter().fold(Gf1024::ZERO, |acc, c| acc * x + *c)
+}
+
+/// Evaluate the polynomial with coefficients from the lowest degree to the highest
+fn eval_rev(poly: &[Gf1024], x: Gf1024) -> Gf1024 {
+    poly.iter().rev().fold(Gf1024::ZERO, |acc, c| acc * x + *c)
+}
+
+/// A corrected Codex32 string with the positions of the changed characters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Correction {
+    pub corrected: Codex32String,
+
+    /// 0-based positions of the changed characters in the string
+    pub positions: Vec<usize>,
+}
+
+/// The corrected string is followed by a line highlighting the changed positions with `^`
+impl Display for Correction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let corrected = self.corrected.to_string();
+        let highlight: String = (0..corrected.len())
+            .map(|i| {
+                if self.positions.contains(&i) {
+                    '^'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+        write!(f, "{corrected}\n{}", highlight.trim_end())
+    }
+}
+
+/// Try to correct up to [`MAX_ERRORS`] substituted characters in the data part of the given
+/// Codex32 string with a short checksum. Characters not in the bech32 alphabet are considered
+/// substitutions too.
+///
+/// Returns `None` if the string is already valid or cannot be corrected.
+pub fn correct(s: &str) -> Option<Correction> {
+    let s = s.trim();
+    if !(48..=N).contains(&s.len()) || !s.is_ascii() {
+        return None;
+    }
+    let uppercase = !s.chars().any(|c| c.is_ascii_lowercase());
+    let lower = s.to_ascii_lowercase();
+    let (hrp, data) = lower.rsplit_once('1')?;
+    let mut data: Vec<Fe> = data
+        .chars()
+        .map(|c| Fe::from_char(c).unwrap_or(Fe::Q))
+        .collect();
+
+    let mut engine = ChecksumEngine::new_codex32_short();
+    engine.input_hrp(hrp).ok()?;
+    data.iter().for_each(|fe| engine.input_fe(*fe));
+
+    // The difference from the target is congruent to the error polynomial modulo the generator,
+    // thus they have the same value on the generator roots
+    let diff: Vec<Gf1024> = engine
+        .into_residue()
+        .into_iter()
+        .zip(TARGET.chars())
+        .map(|(r, t)| (r + Fe::from_char(t).expect("valid target")).into())
+        .collect();
+    let syndromes: Vec<Gf1024> = (0..2 * MAX_ERRORS)
+        .map(|i| eval(&diff, BETA.pow(FIRST_ROOT + i)))
+        .collect();
+
+    let locator = berlekamp_massey(&syndromes)?;
+    let errors = locator.len() - 1;
+
+    // Chien search: the error at distance `p` from the end of the string is located by the root
+    // `BETA^-p` of the locator
+    let positions: Vec<usize> = (0..data.len())
+        .filter(|p| eval_rev(&locator, BETA.pow(N - p)) == Gf1024::ZERO)
+        .collect();
+    if positions.len() != errors {
+        return None;
+    }
+
+    // Forney algorithm
+    let mut evaluator: Vec<Gf1024> = vec![Gf1024::ZERO; 2 * MAX_ERRORS];
+    for (i, s) in syndromes.iter().enumerate() {
+        for (j, l) in locator.iter().enumerate() {
+            if i + j < evaluator.len() {
+                evaluator[i + j] = evaluator[i + j] + *s * *l;
+            }
+        }
+    }
+    let derivative: Vec<Gf1024> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, l)| if i % 2 == 1 { *l } else { Gf1024::ZERO })
+        .collect();
+
+    for p in positions {
+        let x_inv = BETA.pow(N - p);
+        let x_pow = BETA.pow((N - p) * (FIRST_ROOT - 1) % N);
+        let value = x_pow * eval_rev(&evaluator, x_inv) * eval_rev(&derivative, x_inv).inv();
+        if value.1 != Fe::Q || value.0 == Fe::Q {
+            return None;
+        }
+        let index = data.len() - 1 - p;
+        data[index] += value.0;
+    }
+
+    let data: String = data.iter().map(|fe| fe.to_char()).collect();
+    let corrected = format!("{hrp}1{data}");
+    let positions: Vec<usize> = corrected
+        .chars()
+        .zip(lower.chars())
+        .enumerate()
+        .filter_map(|(i, (a, b))| (a != b).then_some(i))
+        .collect();
+    if positions.is_empty() {
+        return None;
+    }
+    let corrected = if uppercase {
+        corrected.to_ascii_uppercase()
+    } else {
+        corrected
+    };
+    let corrected = Codex32String::from_string(corrected).ok()?;
+    Some(Correction {
+        corrected,
+        positions,
+    })
+}
+
+/// Returns the error locator polynomial, coefficients from the lowest degree, or `None` if there
+/// are more than [`MAX_ERRORS`] errors
+fn berlekamp_massey(syndromes: &[Gf1024]) -> Option<Vec<Gf1024>> {
+    let mut current = vec![Gf1024::ONE];
+    let mut previous = vec![Gf1024::ONE];
+    let mut len = 0;
+    let mut shift = 1;
+    let mut previous_discrepancy = Gf1024::ONE;
+
+    for n in 0..syndromes.len() {
+        let discrepancy = (1..=len).fold(syndromes[n], |acc, i| {
+            acc + *current.get(i).unwrap_or(&Gf1024::ZERO) * syndromes[n - i]
+        });
+        if discrepancy == Gf1024::ZERO {
+            shift += 1;
+            continue;
+        }
+        let coef = discrepancy * previous_discrepancy.inv();
+        let mut next = current.clone();
+        next.resize(next.len().max(previous.len() + shift), Gf1024::ZERO);
+        for (i, p) in previous.iter().enumerate() {
+            next[i + shift] = next[i + shift] + coef * *p;
+        }
+        if 2 * len <= n {
+            len = n + 1 - len;
+            previous = current;
+            previous_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+        current = next;
+    }
+
+    current.truncate(len + 1);
+    (len <= MAX_ERRORS).then_some(current)
+}
+
+#[cfg(test)]
+mod test {
+    use codex32::Fe;
+
+    use super::{correct, Gf1024, BETA, FIRST_ROOT, MAX_ERRORS, N};
+
+    const CODEX_32: &str =
+        "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+
+    #[test]
+    fn beta_powers_are_generator_roots() {
+        // generator of the short checksum, from the highest degree
+        let generator: Vec<Gf1024> = std::iter::once(Fe::P)
+            .chain("em3gqeeelmcss".chars().map(|c| Fe::from_char(c).unwrap()))
+            .map(Gf1024::from)
+            .collect();
+        for i in FIRST_ROOT..FIRST_ROOT + 2 * MAX_ERRORS {
+            assert_eq!(super::eval(&generator, BETA.pow(i)), Gf1024::ZERO);
+        }
+        assert_eq!(BETA.pow(N), Gf1024::ONE);
+        assert!([1, 3, 31].iter().all(|d| BETA.pow(*d) != Gf1024::ONE));
+    }
+
+    #[test]
+    fn correct_substitutions() {
+        assert!(correct(CODEX_32).is_none());
+
+        let substitute = |positions: &[usize]| {
+            let mut chars: Vec<char> = CODEX_32.chars().collect();
+            for p in positions {
+                chars[*p] = if chars[*p] == 'q' { 'p' } else { 'q' };
+            }
+            chars.into_iter().collect::<String>()
+        };
+
+        for positions in [
+            vec![3],
+            vec![10, 73],
+            vec![5, 30, 60],
+            vec![4, 20, 21, 72],
+            vec![3, 4, 5, 6],
+        ] {
+            let wrong = substitute(&positions);
+            let correction = correct(&wrong).unwrap();
+            assert_eq!(correction.corrected.to_string(), CODEX_32);
+            assert_eq!(correction.positions, positions);
+        }
+
+        let correction = correct(&substitute(&[10, 11]).to_uppercase()).unwrap();
+        assert_eq!(correction.corrected.to_string(), CODEX_32.to_uppercase());
+
+        let invalid_char = CODEX_32.replacen("leet", "lbet", 1);
+        let correction = correct(&invalid_char).unwrap();
+        assert_eq!(correction.corrected.to_string(), CODEX_32);
+        assert_eq!(correction.positions, vec![5]);
+
+        assert_eq!(
+            correct(&substitute(&[10])).unwrap().to_string(),
+            format!("{CODEX_32}\n          ^")
+        );
+
+        // more than 4 errors could be detected but not corrected
+        assert!(correct(&substitute(&[3, 10, 20, 30, 40]))
+            .map_or(true, |c| c.corrected.to_string() != CODEX_32));
+    }
+}
@@ -1,10 +1,12 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
 
 use bitcoin::{
     bip32::{DerivationPath, ExtendedPubKey},
     secp256k1::Secp256k1,
     Network,
 };
+use clap::ValueEnum;
+use miniscript::descriptor::{DescriptorPublicKey, Wildcard};
 
 use super::{import::explode_descriptor, ImportError, Seed};
 
@@ -15,28 +17,121 @@ pub enum DescriptorError {
 
     #[error(transparent)]
     Import(#[from] ImportError),
+
+    #[error("The key '{0}' must be an extended public key, optionally with origin, without derivation steps")]
+    InvalidKey(String),
+
+    #[error("The key '{0}' is for a different network")]
+    KeyNetwork(String),
+
+    #[error("The heir is supported only with the tr script type")]
+    HeirRequiresTaproot,
+
+    #[error("Cosigners and threshold are supported only with the wsh script type")]
+    CosignersRequireMultisig,
+}
+
+/// The script type of the descriptor, determining also the derivation path of the key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ScriptType {
+    /// Taproot single key, bip86 path `86h/coin/account`
+    #[default]
+    Tr,
+
+    /// Segwit v0 single key, bip84 path `84h/coin/account`
+    Wpkh,
+
+    /// Segwit v0 sorted multisig with the cosigners keys, bip48 path `48h/coin/account/2h`
+    Wsh,
+}
+
+/// The condition after which the heir could spend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timelock {
+    /// Relative timelock, the number of blocks since the output has been confirmed
+    Older(u16),
+
+    /// Absolute timelock, a block height or a unix timestamp if greater than 500 millions
+    After(u32),
+}
+
+impl Display for Timelock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Timelock::Older(blocks) => write!(f, "older({blocks})"),
+            Timelock::After(lock) => write!(f, "after({lock})"),
+        }
+    }
+}
+
+/// A script path giving the heir the ability to spend once the timelock expires
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heir {
+    /// Extended public key of the heir, like `[fingerprint/path]xpub`
+    pub key: String,
+
+    pub timelock: Timelock,
+}
+
+/// The shape of the descriptor built around the key derived from the seed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DescriptorTemplate {
+    /// `tr(key)` or, with the heir, `tr(key,and_v(v:pk(heir),older(N)))`
+    Taproot { heir: Option<Heir> },
+
+    /// `wpkh(key)`
+    Wpkh,
+
+    /// `wsh(sortedmulti(threshold,key,cosigners...))`, cosigners are extended public keys like
+    /// `[fingerprint/path]xpub`
+    SortedMulti {
+        threshold: usize,
+        cosigners: Vec<String>,
+    },
+}
+
+impl Default for DescriptorTemplate {
+    fn default() -> Self {
+        DescriptorTemplate::Taproot { heir: None }
+    }
 }
 
+impl DescriptorTemplate {
+    fn path(&self, coin_type: u32, account: u16) -> String {
+        match self {
+            DescriptorTemplate::Taproot { .. } => format!("86h/{coin_type}h/{account}h"),
+            DescriptorTemplate::Wpkh => format!("84h/{coin_type}h/{account}h"),
+            DescriptorTemplate::SortedMulti { .. } => format!("48h/{coin_type}h/{account}h/2h"),
+        }
+    }
+}
+
+/// Given a seed, an account and if public or private, returns the descriptor of the given
+/// template, with the key derived at the path of the script type: bip86, bip84 or bip48.
+///
+/// If the heir is given, the owner key is the taproot internal key and the heir key is in a script
+/// path spendable only after the timelock, like `tr(owner,and_v(v:pk(heir),older(N)))`.
 pub fn descriptor(
     seed: Seed,
     network: Network,
     account: u16,
     public: bool,
+    template: &DescriptorTemplate,
 ) -> Result<String, DescriptorError> {
     let coin_type = match network {
         Network::Bitcoin => 0,
         _ => 1,
     };
 
-    let path_str = format!("86h/{coin_type}h/{account}h");
+    let path_str = template.path(coin_type, account);
 
-    let bip86_path = DerivationPath::from_str(&format!("m/{path_str}"))
+    let path = DerivationPath::from_str(&format!("m/{path_str}"))
         .expect("coin type [0,1] and account (u16) cannot exceed valid values");
 
     let fingerprint = seed.fingerprint()?;
 
     let secp = Secp256k1::new();
-    let xprv = seed.xprv(network)?.derive_priv(&secp, &bip86_path)?;
+    let xprv = seed.xprv(network)?.derive_priv(&secp, &path)?;
 
     let xkey = if public {
         ExtendedPubKey::from_priv(&secp, &xprv).to_string()
@@ -44,18 +139,141 @@ pub fn descriptor(
         xprv.to_string()
     };
 
-    let desc = format!("tr([{fingerprint}/{path_str}]{xkey}/<0;1>/*)");
+    let owner = format!("[{fingerprint}/{path_str}]{xkey}/<0;1>/*");
+    let desc = match template {
+        DescriptorTemplate::Taproot {
+            heir: Some(Heir { key, timelock }),
+        } => {
+            check_key(key, network)?;
+            format!("tr({owner},and_v(v:pk({key}/<0;1>/*),{timelock}))")
+        }
+        DescriptorTemplate::Taproot { heir: None } => format!("tr({owner})"),
+        DescriptorTemplate::Wpkh => format!("wpkh({owner})"),
+        DescriptorTemplate::SortedMulti {
+            threshold,
+            cosigners,
+        } => {
+            let mut keys = vec![owner];
+            for key in cosigners {
+                check_key(key, network)?;
+                keys.push(format!("{key}/<0;1>/*"));
+            }
+            format!("wsh(sortedmulti({threshold},{}))", keys.join(","))
+        }
+    };
 
     let _ = explode_descriptor(&desc, !public)?;
 
     Ok(desc)
 }
 
+fn check_key(key: &str, network: Network) -> Result<(), DescriptorError> {
+    let invalid = || DescriptorError::InvalidKey(key.to_string());
+    match DescriptorPublicKey::from_str(key).map_err(|_| invalid())? {
+        DescriptorPublicKey::XPub(xpub)
+            if xpub.derivation_path.is_empty() && xpub.wildcard == Wildcard::None =>
+        {
+            if (xpub.xkey.network == Network::Bitcoin) != (network == Network::Bitcoin) {
+                return Err(DescriptorError::KeyNetwork(key.to_string()));
+            }
+            Ok(())
+        }
+        _ => Err(invalid()),
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use super::{descriptor, DescriptorError, DescriptorTemplate, Heir, Timelock};
     use crate::commands::Seed;
+    use bitcoin::Network;
     use std::str::FromStr;
 
+    const CODEX_32: &str =
+        "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+    const HEIR: &str = "[8335dcdb/48h/1h/0h/2h]tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1";
+
+    #[test]
+    fn test_heir() {
+        let seed = Seed::from_str(CODEX_32).unwrap();
+        let heir = |key: &str, timelock| DescriptorTemplate::Taproot {
+            heir: Some(Heir {
+                key: key.to_string(),
+                timelock,
+            }),
+        };
+
+        let older = heir(HEIR, Timelock::Older(1000));
+        let desc = descriptor(seed.clone(), Network::Regtest, 0, true, &older).unwrap();
+        assert_eq!(desc, format!("tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*,and_v(v:pk({HEIR}/<0;1>/*),older(1000)))"));
+
+        let after = heir(HEIR, Timelock::After(900_000));
+        let desc = descriptor(seed.clone(), Network::Regtest, 0, false, &after).unwrap();
+        assert!(desc.starts_with("tr([01e0b4da/86h/1h/0h]tprv"));
+        assert!(desc.ends_with("/<0;1>/*),after(900000)))"));
+
+        for invalid in [
+            "not a key",
+            "03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd",
+            &format!("{HEIR}/0/*"),
+        ] {
+            let result = descriptor(
+                seed.clone(),
+                Network::Regtest,
+                0,
+                true,
+                &heir(invalid, Timelock::Older(1000)),
+            );
+            assert!(matches!(result, Err(DescriptorError::InvalidKey(_))));
+        }
+
+        let result = descriptor(seed.clone(), Network::Bitcoin, 0, true, &older);
+        assert!(matches!(result, Err(DescriptorError::KeyNetwork(_))));
+
+        let zero = heir(HEIR, Timelock::Older(0));
+        let result = descriptor(seed, Network::Regtest, 0, true, &zero);
+        assert!(matches!(result, Err(DescriptorError::Import(_))));
+    }
+
+    #[test]
+    fn test_script_types() {
+        let seed = Seed::from_str(CODEX_32).unwrap();
+
+        let desc = descriptor(
+            seed.clone(),
+            Network::Regtest,
+            0,
+            true,
+            &DescriptorTemplate::Wpkh,
+        )
+        .unwrap();
+        assert!(desc.starts_with("wpkh([01e0b4da/84h/1h/0h]tpub"));
+        assert!(desc.ends_with("/<0;1>/*)"));
+
+        let multi = DescriptorTemplate::SortedMulti {
+            threshold: 2,
+            cosigners: vec![HEIR.to_string()],
+        };
+        let desc = descriptor(seed.clone(), Network::Regtest, 0, true, &multi).unwrap();
+        assert!(desc.starts_with("wsh(sortedmulti(2,[01e0b4da/48h/1h/0h/2h]tpub"));
+        assert!(desc.ends_with(&format!(",{HEIR}/<0;1>/*))")));
+
+        // the bip48 key of the cosigner is the one derived from its seed
+        let cosigner = Seed::from_str(
+            "alter trial legal chuckle wear mansion sweet invest shy cabin autumn ribbon",
+        )
+        .unwrap();
+        let desc = descriptor(cosigner, Network::Testnet, 0, true, &multi).unwrap();
+        assert!(desc.contains(&format!("sortedmulti(2,{HEIR}/<0;1>/*,")));
+
+        let too_many = DescriptorTemplate::SortedMulti {
+            threshold: 3,
+            cosigners: vec![HEIR.to_string()],
+        };
+        let result = descriptor(seed, Network::Regtest, 0, true, &too_many);
+        assert!(matches!(result, Err(DescriptorError::Import(_))));
+    }
+
     #[test]
     fn test_liana() {
         let mnemonic =
@@ -2,8 +2,9 @@ use bitcoin::{
     absolute, psbt::PartiallySignedTransaction, Address, Amount, Network, ScriptBuf, SignedAmount,
     Txid,
 };
-use std::{collections::HashSet, fmt::Display};
+use std::{collections::HashMap, fmt::Display};
 
+use super::{import::single_descriptors, ImportError};
 use crate::Descriptor;
 
 #[derive(Debug, thiserror::Error)]
@@ -16,6 +17,9 @@ pub enum BalanceError {
 
     #[error(transparent)]
     Miniscript(#[from] miniscript::Error),
+
+    #[error(transparent)]
+    Import(#[from] ImportError),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -49,7 +53,7 @@ pub fn psbt_details(
     let my_scripts = MyScripts::new(descriptor, 1_000)?;
 
     for (i, psbt) in psbts.iter().enumerate() {
-        let balance = psbt_detail(i, &psbt, &my_scripts, network)?;
+        let balance = psbt_detail(i, psbt, &my_scripts, network)?;
         group.merge(balance);
     }
 
@@ -67,9 +71,21 @@ impl GroupDetail {
         self.incoming.to_signed().unwrap() - self.outgoing.to_signed().unwrap()
     }
 }
-struct MyScripts {
+/// Where a script has been derived from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ScriptOrigin {
+    /// Position of the multipath descriptor in the given ones
+    pub descriptor: usize,
+
+    /// 0 for the receive branch, 1 for the change branch
+    pub branch: usize,
+
+    pub index: u32,
+}
+
+pub(crate) struct MyScripts {
     descriptors: Vec<String>,
-    cache: HashSet<ScriptBuf>,
+    cache: HashMap<ScriptBuf, ScriptOrigin>,
 }
 
 impl MyScripts {
@@ -77,14 +93,20 @@ impl MyScripts {
         multi_descriptors: &[Descriptor],
         how_many_per_desc: u32,
     ) -> Result<Self, BalanceError> {
-        let mut cache = HashSet::new();
+        let mut cache = HashMap::new();
         let mut descriptors = vec![];
-        for multi_descriptor in multi_descriptors {
-            for descriptor in multi_descriptor.clone().into_single_descriptors()? {
+        for (d, multi_descriptor) in multi_descriptors.iter().enumerate() {
+            let singles = single_descriptors(multi_descriptor)?;
+            for (branch, descriptor) in singles.into_iter().enumerate() {
                 descriptors.push(descriptor.to_string());
                 for i in 0..how_many_per_desc {
                     let derived = descriptor.at_derivation_index(i)?;
-                    cache.insert(derived.script_pubkey());
+                    let origin = ScriptOrigin {
+                        descriptor: d,
+                        branch,
+                        index: i,
+                    };
+                    cache.insert(derived.script_pubkey(), origin);
                 }
             }
         }
@@ -92,7 +114,10 @@ impl MyScripts {
         Ok(Self { descriptors, cache })
     }
     pub fn contains(&self, script_pubkey: &ScriptBuf) -> bool {
-        self.cache.contains(script_pubkey)
+        self.cache.contains_key(script_pubkey)
+    }
+    pub fn get(&self, script_pubkey: &ScriptBuf) -> Option<&ScriptOrigin> {
+        self.cache.get(script_pubkey)
     }
     pub fn descriptors(&self) -> &[String] {
         &self.descriptors
@@ -240,7 +265,7 @@ mod test {
             balance,
             PsbtDetail {
                 i: 0,
-                txid: txid.clone(),
+                txid,
                 lock_time: absolute::LockTime::from_height(0).unwrap(),
                 descriptors: vec!["tr(tpubD6NzVbkrYhZ4XUprtHTHAWupukJFpWBJBBU9pyp62LVMhxnpb1dqDouxv5m2MTTAuWzLvFQmtgWwzHCFTrVXi1HscGm1BZ2xuGDN5KL4zNF/0/*)#xq5v4q9d".to_string(), "tr(tpubD6NzVbkrYhZ4XUprtHTHAWupukJFpWBJBBU9pyp62LVMhxnpb1dqDouxv5m2MTTAuWzLvFQmtgWwzHCFTrVXi1HscGm1BZ2xuGDN5KL4zNF/1/*)#h53dg444".to_string()],
                 outgoing: Amount::from_btc(50.0).unwrap(),
@@ -0,0 +1,183 @@
+use std::{
+    io::{Read, Write},
+    iter,
+    str::FromStr,
+};
+
+use age::{
+    armor::{ArmoredReader, ArmoredWriter, Format},
+    x25519, Decryptor, Encryptor,
+};
+
+use super::{
+    hardened_identity, identity, identity::IDENTITY_HRP, identity_combine, IdentityError,
+    IdentityPath, Seed, SeedError,
+};
+
+const SECRET_KEY_PREFIX: &str = "AGE-SECRET-KEY-";
+
+#[derive(thiserror::Error, Debug)]
+pub enum EncryptError {
+    #[error(transparent)]
+    Encrypt(#[from] age::EncryptError),
+
+    #[error(transparent)]
+    Decrypt(#[from] age::DecryptError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Identity(#[from] IdentityError),
+
+    #[error(transparent)]
+    Seed(#[from] SeedError),
+
+    #[error("At least one recipient is required")]
+    NoRecipients,
+
+    #[error("Invalid age identity: {0}")]
+    InvalidIdentity(&'static str),
+
+    #[error("Files encrypted with a passphrase are not supported")]
+    PassphraseEncrypted,
+
+    #[error("Index, label and passphrases apply only to identities derived from a seed")]
+    DerivationWithoutSeed,
+}
+
+/// Encrypt the plaintext to the given age recipients, if `armor` the result is ascii armored
+pub fn encrypt(
+    plaintext: &[u8],
+    recipients: &[x25519::Recipient],
+    armor: bool,
+) -> Result<Vec<u8>, EncryptError> {
+    let recipients = recipients
+        .iter()
+        .map(|r| Box::new(r.clone()) as Box<dyn age::Recipient + Send>)
+        .collect();
+    let encryptor = Encryptor::with_recipients(recipients).ok_or(EncryptError::NoRecipients)?;
+
+    let format = if armor {
+        Format::AsciiArmor
+    } else {
+        Format::Binary
+    };
+    let mut result = vec![];
+    let armored = ArmoredWriter::wrap_output(&mut result, format)?;
+    let mut writer = encryptor.wrap_output(armored)?;
+    writer.write_all(plaintext)?;
+    writer.finish()?.finish()?;
+
+    Ok(result)
+}
+
+/// Decrypt the ciphertext, either armored or binary, with the given identity
+pub fn decrypt(ciphertext: &[u8], identity: &x25519::Identity) -> Result<Vec<u8>, EncryptError> {
+    let decryptor = match Decryptor::new(ArmoredReader::new(ciphertext))? {
+        Decryptor::Recipients(d) => d,
+        Decryptor::Passphrase(_) => return Err(EncryptError::PassphraseEncrypted),
+    };
+    let mut reader = decryptor.decrypt(iter::once(identity as &dyn age::Identity))?;
+    let mut result = vec![];
+    reader.read_to_end(&mut result)?;
+
+    Ok(result)
+}
+
+/// The secret could be an age identity like `AGE-SECRET-KEY-1...`, enough Codex32 shares of an
+/// identity, one per line, or a seed from which the identity is derived with the given passphrase
+/// and path, hardened if `kdf_passphrase` is given.
+///
+/// Passphrases and a path other than [`IdentityPath::Master`] are rejected if the secret is not a
+/// seed, since they cannot change the identity.
+pub fn to_identity(
+    secret: &str,
+    passphrase: &str,
+    path: &IdentityPath,
+    kdf_passphrase: Option<&str>,
+) -> Result<x25519::Identity, EncryptError> {
+    let secret = secret.trim();
+    let derivation =
+        !passphrase.is_empty() || *path != IdentityPath::Master || kdf_passphrase.is_some();
+    let not_seed = || {
+        if derivation {
+            Err(EncryptError::DerivationWithoutSeed)
+        } else {
+            Ok(())
+        }
+    };
+    if secret.to_uppercase().starts_with(SECRET_KEY_PREFIX) {
+        not_seed()?;
+        x25519::Identity::from_str(&secret.to_uppercase()).map_err(EncryptError::InvalidIdentity)
+    } else if secret
+        .to_lowercase()
+        .starts_with(&format!("{IDENTITY_HRP}1"))
+    {
+        not_seed()?;
+        let shares: Vec<_> = secret.lines().map(ToString::to_string).collect();
+        Ok(identity_combine(&shares)?)
+    } else {
+        let seed = Seed::from_str(secret)?.with_passphrase(passphrase);
+        Ok(match kdf_passphrase {
+            Some(kdf_passphrase) => hardened_identity(&seed, path, kdf_passphrase)?,
+            None => identity(&seed, path)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use age::secrecy::ExposeSecret;
+
+    use super::{decrypt, encrypt, to_identity, EncryptError};
+    use crate::commands::IdentityPath;
+
+    const MNEMONIC: &str = "flock audit wash crater album salon goose december envelope scissors lock suit render endorse prevent radio expose defy squirrel into grace broken culture burden";
+
+    #[test]
+    fn roundtrip() {
+        let identity = to_identity(MNEMONIC, "", &IdentityPath::Master, None).unwrap();
+        let other = to_identity(MNEMONIC, "", &IdentityPath::Index(0), None).unwrap();
+        let key = identity.to_string();
+        let key = key.expose_secret();
+        let from_key = to_identity(key, "", &IdentityPath::Master, None).unwrap();
+        assert_eq!(
+            from_key.to_public().to_string(),
+            identity.to_public().to_string()
+        );
+        for (passphrase, path, kdf_passphrase) in [
+            ("", IdentityPath::Index(3), None),
+            ("", IdentityPath::Label("alice".to_string()), None),
+            ("TREZOR", IdentityPath::Master, None),
+            ("", IdentityPath::Master, Some("kdf secret")),
+        ] {
+            assert!(matches!(
+                to_identity(key, passphrase, &path, kdf_passphrase),
+                Err(EncryptError::DerivationWithoutSeed)
+            ));
+        }
+
+        for armor in [true, false] {
+            let ciphertext = encrypt(b"hello", &[identity.to_public()], armor).unwrap();
+            assert_eq!(
+                ciphertext.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----"),
+                armor
+            );
+            assert_eq!(decrypt(&ciphertext, &identity).unwrap(), b"hello");
+            assert!(matches!(
+                decrypt(&ciphertext, &other),
+                Err(EncryptError::Decrypt(_))
+            ));
+        }
+
+        let both = encrypt(b"hello", &[identity.to_public(), other.to_public()], false).unwrap();
+        assert_eq!(decrypt(&both, &identity).unwrap(), b"hello");
+        assert_eq!(decrypt(&both, &other).unwrap(), b"hello");
+
+        assert!(matches!(
+            encrypt(b"hello", &[], false),
+            Err(EncryptError::NoRecipients)
+        ));
+    }
+}
@@ -0,0 +1,401 @@
+use std::fmt::Display;
+
+use bitcoin::{
+    hashes::{sha256, Hash, HashEngine},
+    secp256k1::rand::{rngs::OsRng, RngCore},
+};
+use clap::ValueEnum;
+
+use super::SeedError;
+
+/// Bits of entropy required to create a seed
+const NEEDED_BITS: f64 = 256.0;
+
+/// Standard normal quantile of the significance level of the chi-square test (p=0.001)
+const Z_SIGNIFICANCE: f64 = 3.090;
+
+/// A run is suspicious if the expected number of runs so long is lower than this value
+const RUN_SIGNIFICANCE: f64 = 0.001;
+
+const RANKS: &str = "A23456789TJQK";
+const SUITS: &str = "SHDC";
+
+/// The physical source of randomness used to create the seed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum EntropySource {
+    /// Launches of a 6-side dice, one character per launch: `1`..`6`
+    #[default]
+    Dice,
+
+    /// Coin flips, one character per flip: `0` or `1`
+    Coin,
+
+    /// Launches of a 8-side dice, one character per launch: `1`..`8`
+    D8,
+
+    /// Launches of a 20-side dice, numbers from `1` to `20` separated by spaces or commas
+    D20,
+
+    /// Shuffled decks of 52 cards, like `AS TD 9H`, rank `A23456789TJQK` and suit `SHDC`.
+    /// Every 52 cards a new shuffled deck starts.
+    Cards,
+
+    /// Hexadecimal characters, for example from another random number generator
+    Hex,
+}
+
+impl EntropySource {
+    /// Validate the sequence and returns the sha256 of its normalized form as entropy
+    pub(crate) fn entropy(&self, sequence: &str) -> Result<[u8; 32], SeedError> {
+        let symbols = self.symbols(sequence)?;
+
+        let needed = self.needed();
+        if symbols.len() < needed {
+            return Err(SeedError::TooShort(symbols.len(), needed));
+        }
+
+        let normalized = match self {
+            EntropySource::D20 | EntropySource::Cards => symbols.join(" "),
+            _ => symbols.concat(),
+        };
+
+        Ok(sha256::Hash::hash(normalized.as_bytes()).to_byte_array())
+    }
+
+    /// Minimum number of symbols needed to achieve 256 bits of entropy
+    pub fn needed(&self) -> usize {
+        match self {
+            EntropySource::Cards => {
+                let mut bits = 0f64;
+                let mut cards = 0;
+                while bits < NEEDED_BITS {
+                    bits += ((52 - cards % 52) as f64).log2();
+                    cards += 1;
+                }
+                cards
+            }
+            // 99 launches, about 255.9 bits, kept for compatibility with existing dice seeds
+            EntropySource::Dice => (NEEDED_BITS / (self.faces() as f64).log2()) as usize,
+            _ => (NEEDED_BITS / (self.faces() as f64).log2()).ceil() as usize,
+        }
+    }
+
+    /// Number of possible outcomes of a single event
+    pub(crate) fn faces(&self) -> usize {
+        match self {
+            EntropySource::Dice => 6,
+            EntropySource::Coin => 2,
+            EntropySource::D8 => 8,
+            EntropySource::D20 => 20,
+            EntropySource::Cards => 52,
+            EntropySource::Hex => 16,
+        }
+    }
+
+    fn dictionary(&self) -> &'static str {
+        match self {
+            EntropySource::Dice => "123456",
+            EntropySource::Coin => "01",
+            EntropySource::D8 => "12345678",
+            EntropySource::D20 | EntropySource::Cards => "",
+            EntropySource::Hex => "0123456789abcdef",
+        }
+    }
+
+    /// Split the sequence in symbols, validating each of them
+    pub(crate) fn symbols(&self, sequence: &str) -> Result<Vec<String>, SeedError> {
+        match self {
+            EntropySource::D20 => split_tokens(sequence)
+                .map(|t| match t.parse::<u8>() {
+                    Ok(n) if (1..=20).contains(&n) => Ok(n.to_string()),
+                    _ => Err(SeedError::InvalidSymbol(t.to_string())),
+                })
+                .collect(),
+            EntropySource::Cards => {
+                let cards = split_tokens(sequence)
+                    .map(|t| {
+                        let card = t.to_ascii_uppercase();
+                        let mut chars = card.chars();
+                        match (chars.next(), chars.next(), chars.next()) {
+                            (Some(rank), Some(suit), None)
+                                if RANKS.contains(rank) && SUITS.contains(suit) =>
+                            {
+                                Ok(card)
+                            }
+                            _ => Err(SeedError::InvalidSymbol(t.to_string())),
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                for deck in cards.chunks(52) {
+                    for (i, card) in deck.iter().enumerate() {
+                        if deck[..i].contains(card) {
+                            return Err(SeedError::RepeatedCard(card.to_string()));
+                        }
+                    }
+                }
+                Ok(cards)
+            }
+            _ => {
+                let dictionary = self.dictionary();
+                let sequence = match self {
+                    EntropySource::Hex => sequence.to_ascii_lowercase(),
+                    _ => sequence.to_string(),
+                };
+                sequence
+                    .chars()
+                    .map(|c| {
+                        if dictionary.contains(c) {
+                            Ok(c.to_string())
+                        } else {
+                            Err(SeedError::NonDictionaryChar(c, dictionary))
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// 32 bytes from the random number generator of the operating system
+pub fn system_random() -> [u8; 32] {
+    let mut random = [0u8; 32];
+    OsRng.fill_bytes(&mut random);
+    random
+}
+
+/// Mix the entropy of the sequence with `random` bytes, the result is unpredictable if any of the
+/// two is, so that neither a biased sequence nor a flawed generator alone weakens the seed.
+pub(crate) fn mix(entropy: [u8; 32], random: &[u8; 32]) -> [u8; 32] {
+    let mut engine = sha256::Hash::engine();
+    engine.input(&entropy);
+    engine.input(random);
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// Statistical sanity report of a random sequence
+#[derive(Debug)]
+pub struct EntropyReport {
+    /// Occurrences of every face, in dictionary order
+    frequencies: Vec<(String, usize)>,
+
+    /// Longest sequence of equal consecutive symbols
+    longest_run: (String, usize),
+
+    /// Longest run considered plausible for this sequence length
+    max_run: usize,
+
+    /// Chi-square statistic with the critical value, `None` when not meaningful (cards)
+    chi_square: Option<(f64, f64)>,
+}
+
+impl EntropySource {
+    /// Compute the statistical sanity report of the sequence
+    pub fn report(&self, sequence: &str) -> Result<EntropyReport, SeedError> {
+        let symbols = self.symbols(sequence)?;
+        let faces = self.faces();
+
+        let frequencies: Vec<_> = self
+            .faces_symbols()
+            .into_iter()
+            .map(|face| {
+                let count = symbols.iter().filter(|s| **s == face).count();
+                (face, count)
+            })
+            .collect();
+
+        let mut longest_run = (String::new(), 0);
+        let mut current = 0;
+        for (i, symbol) in symbols.iter().enumerate() {
+            if i > 0 && symbols[i - 1] == *symbol {
+                current += 1;
+            } else {
+                current = 1;
+            }
+            if current > longest_run.1 {
+                longest_run = (symbol.to_string(), current);
+            }
+        }
+
+        // the expected number of runs of length `l` is about `n * (1/faces)^(l-1)`
+        let n = symbols.len().max(1) as f64;
+        let max_run = 1 + ((n / RUN_SIGNIFICANCE).ln() / (faces as f64).ln()) as usize;
+
+        // shuffled decks have every card exactly once, frequencies aren't random
+        let chi_square = (*self != EntropySource::Cards).then(|| {
+            let expected = n / faces as f64;
+            let statistic = frequencies
+                .iter()
+                .map(|(_, count)| (*count as f64 - expected).powi(2) / expected)
+                .sum();
+            (statistic, chi_square_critical(faces - 1))
+        });
+
+        Ok(EntropyReport {
+            frequencies,
+            longest_run,
+            max_run,
+            chi_square,
+        })
+    }
+
+    fn faces_symbols(&self) -> Vec<String> {
+        match self {
+            EntropySource::D20 => (1..=20).map(|n| n.to_string()).collect(),
+            EntropySource::Cards => RANKS
+                .chars()
+                .flat_map(|r| SUITS.chars().map(move |s| format!("{r}{s}")))
+                .collect(),
+            _ => self.dictionary().chars().map(|c| c.to_string()).collect(),
+        }
+    }
+}
+
+impl EntropyReport {
+    /// Returns an error if the sequence doesn't look random
+    pub fn check(&self) -> Result<(), SeedError> {
+        if self.longest_run.1 > self.max_run {
+            return Err(SeedError::NonRandom(format!(
+                "{} repeated {} times in a row",
+                self.longest_run.0, self.longest_run.1
+            )));
+        }
+        if let Some((statistic, critical)) = self.chi_square {
+            if statistic > critical {
+                return Err(SeedError::NonRandom(format!(
+                    "chi-square {statistic:.2} is over the critical value {critical:.2}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for EntropyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (face, count) in self.frequencies.iter() {
+            writeln!(f, "{face:>3}: {count}")?;
+        }
+        writeln!(
+            f,
+            "longest run: {} repeated {} times (max {})",
+            self.longest_run.0, self.longest_run.1, self.max_run
+        )?;
+        match self.chi_square {
+            Some((statistic, critical)) => {
+                write!(f, "chi-square: {statistic:.2} (critical {critical:.2})")
+            }
+            None => write!(f, "chi-square: not applicable"),
+        }
+    }
+}
+
+/// Wilson–Hilferty approximation of the chi-square critical value
+fn chi_square_critical(degrees_of_freedom: usize) -> f64 {
+    let k = degrees_of_freedom as f64;
+    let a = 2.0 / (9.0 * k);
+    k * (1.0 - a + Z_SIGNIFICANCE * a.sqrt()).powi(3)
+}
+
+fn split_tokens(sequence: &str) -> impl Iterator<Item = &str> {
+    sequence
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{chi_square_critical, EntropySource};
+    use crate::commands::SeedError;
+
+    #[test]
+    fn needed() {
+        assert_eq!(EntropySource::Dice.needed(), 99);
+        assert_eq!(EntropySource::Coin.needed(), 256);
+        assert_eq!(EntropySource::D8.needed(), 86);
+        assert_eq!(EntropySource::D20.needed(), 60);
+        assert_eq!(EntropySource::Cards.needed(), 58);
+        assert_eq!(EntropySource::Hex.needed(), 64);
+    }
+
+    #[test]
+    fn sources() {
+        let coins = "01".repeat(128);
+        EntropySource::Coin.entropy(&coins).unwrap();
+        assert!(matches!(
+            EntropySource::Coin.entropy(&coins[1..]),
+            Err(SeedError::TooShort(255, 256))
+        ));
+        assert!(matches!(
+            EntropySource::Coin.entropy(&coins.replace('1', "2")),
+            Err(SeedError::NonDictionaryChar('2', _))
+        ));
+
+        let hex = "aB".repeat(32);
+        assert_eq!(
+            EntropySource::Hex.entropy(&hex).unwrap(),
+            EntropySource::Hex
+                .entropy(&hex.to_ascii_lowercase())
+                .unwrap(),
+        );
+
+        let d20 = (1..=20)
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let d20 = [d20.as_str(), d20.as_str(), d20.as_str()].join(",");
+        EntropySource::D20.entropy(&d20).unwrap();
+        assert!(matches!(
+            EntropySource::D20.entropy(&d20.replace("17", "21")),
+            Err(SeedError::InvalidSymbol(_))
+        ));
+
+        let deck: Vec<_> = "A23456789TJQK"
+            .chars()
+            .flat_map(|r| "SHDC".chars().map(move |s| format!("{r}{s}")))
+            .collect();
+        let cards = format!("{} {}", deck.join(" "), deck[..6].join(" "));
+        EntropySource::Cards.entropy(&cards).unwrap();
+        let short = format!("{} {}", deck.join(" "), deck[..5].join(" "));
+        assert!(matches!(
+            EntropySource::Cards.entropy(&short),
+            Err(SeedError::TooShort(57, 58))
+        ));
+        let repeated = format!("AS {}", deck[1..].join(" ")).replace("2S", "AS");
+        assert!(matches!(
+            EntropySource::Cards.entropy(&format!("{repeated} {}", deck[..6].join(" "))),
+            Err(SeedError::RepeatedCard(_))
+        ));
+    }
+
+    #[test]
+    fn report() {
+        let dices = "43242535241352135351234134123421351351342134123412351341324134134213512512353513123423423433222413233";
+        let report = EntropySource::Dice.report(dices).unwrap();
+        assert!(
+            matches!(report.check(), Err(SeedError::NonRandom(_))),
+            "missing 6"
+        );
+
+        let dices = "6".repeat(99);
+        let report = EntropySource::Dice.report(&dices).unwrap();
+        assert_eq!(report.longest_run, ("6".to_string(), 99));
+        assert!(report.check().is_err());
+
+        let dices = "123456".repeat(17);
+        let report = EntropySource::Dice.report(&dices).unwrap();
+        report.check().unwrap();
+        assert!(report.to_string().starts_with("  1: 17\n  2: 17"));
+
+        let coins = "0110100110010110".repeat(16);
+        EntropySource::Coin.report(&coins).unwrap().check().unwrap();
+        let coins = format!("{}{}", "0".repeat(30), &coins[30..]);
+        assert!(EntropySource::Coin.report(&coins).unwrap().check().is_err());
+    }
+
+    #[test]
+    fn critical() {
+        // reference values from chi-square table at p=0.001
+        assert!((chi_square_critical(5) - 20.515).abs() < 0.3);
+        assert!((chi_square_critical(15) - 37.697).abs() < 0.3);
+    }
+}
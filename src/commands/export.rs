@@ -0,0 +1,286 @@
+use bitcoin::{
+    bip32::{ChildNumber, DerivationPath, Fingerprint},
+    Network,
+};
+use bitcoind::bitcoincore_rpc::{
+    core_rpc_json::{ImportDescriptors, Timestamp},
+    jsonrpc::serde_json,
+};
+use clap::ValueEnum;
+use miniscript::{descriptor::checksum::desc_checksum, DescriptorPublicKey, ForEachKey};
+use serde::Serialize;
+
+use super::import::{single_descriptors, Multipath};
+use crate::Descriptor;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+    #[error(transparent)]
+    Miniscript(#[from] miniscript::Error),
+
+    #[error(transparent)]
+    Conversion(#[from] miniscript::descriptor::ConversionError),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("The descriptor must be multipath with receive and change branches like `/<0;1>/*`")]
+    NotMultipath,
+
+    #[error("The bsms format requires the same tuple on every key, followed only by `/*`")]
+    UnsupportedBsms,
+
+    #[error("The coldcard format supports only single key tr or wpkh descriptors with key origin")]
+    UnsupportedColdcard,
+}
+
+/// The file format understood by other wallets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Bip129 Bitcoin Secure Multisig Setup descriptor record
+    Bsms,
+
+    /// Specter wallet JSON, imported also by Sparrow
+    Specter,
+
+    /// Coldcard generic JSON, only for single key descriptors
+    Coldcard,
+
+    /// The payload of the bitcoin core `importdescriptors` call
+    Core,
+}
+
+#[derive(Serialize)]
+struct SpecterWallet {
+    label: String,
+    blockheight: u32,
+    descriptor: String,
+    devices: Vec<SpecterDevice>,
+}
+
+#[derive(Serialize)]
+struct SpecterDevice {
+    #[serde(rename = "type")]
+    kind: String,
+    label: String,
+}
+
+#[derive(Serialize)]
+struct ColdcardWallet {
+    chain: String,
+    xfp: String,
+    account: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bip84: Option<ColdcardSection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bip86: Option<ColdcardSection>,
+}
+
+#[derive(Serialize)]
+struct ColdcardSection {
+    name: String,
+    deriv: String,
+    xpub: String,
+    xfp: String,
+    first: String,
+}
+
+/// Convert the public multipath descriptor in a file importable by other wallets.
+///
+/// Wallets are created with birthday at the genesis block, so that a full rescan finds all the
+/// coins. The `label` is the wallet name where the format supports it.
+pub fn export(
+    descriptor: &Descriptor,
+    network: Network,
+    format: ExportFormat,
+    label: &str,
+) -> Result<String, ExportError> {
+    let singles = single_descriptors(descriptor).map_err(|_| ExportError::NotMultipath)?;
+    if singles.len() != 2 {
+        return Err(ExportError::NotMultipath);
+    }
+    let (external, internal) = (&singles[0], &singles[1]);
+    let first = external
+        .at_derivation_index(0)?
+        .address(network)?
+        .to_string();
+
+    Ok(match format {
+        ExportFormat::Bsms => {
+            let (template, paths) = bsms_template(&descriptor.to_string())?;
+            let checksum = desc_checksum(&template)?;
+            format!("BSMS 1.0\n{template}#{checksum}\n{paths}\n{first}")
+        }
+        ExportFormat::Specter => {
+            let mut devices: Vec<SpecterDevice> = vec![];
+            descriptor.for_each_key(|key| {
+                let label = key.master_fingerprint().to_string();
+                if devices.iter().all(|d| d.label != label) {
+                    devices.push(SpecterDevice {
+                        kind: "other".to_string(),
+                        label,
+                    });
+                }
+                true
+            });
+            serde_json::to_string_pretty(&SpecterWallet {
+                label: label.to_string(),
+                blockheight: 0,
+                descriptor: external.to_string(),
+                devices,
+            })?
+        }
+        ExportFormat::Coldcard => {
+            let (fingerprint, path, xpub) =
+                single_key(descriptor).ok_or(ExportError::UnsupportedColdcard)?;
+            let xfp = fingerprint.to_string().to_uppercase();
+            let is_tr = matches!(descriptor, Descriptor::Tr(_));
+            let section = Some(ColdcardSection {
+                name: if is_tr { "p2tr" } else { "p2wpkh" }.to_string(),
+                deriv: path.to_string(),
+                xpub,
+                xfp: xfp.clone(),
+                first,
+            });
+            let (bip84, bip86) = if is_tr {
+                (None, section)
+            } else {
+                (section, None)
+            };
+            let account = match path.into_iter().last() {
+                Some(ChildNumber::Normal { index } | ChildNumber::Hardened { index }) => *index,
+                None => 0,
+            };
+            serde_json::to_string_pretty(&ColdcardWallet {
+                chain: match network {
+                    Network::Bitcoin => "BTC",
+                    _ => "XTN",
+                }
+                .to_string(),
+                xfp,
+                account,
+                bip84,
+                bip86,
+            })?
+        }
+        ExportFormat::Core => {
+            let request = |desc: &Descriptor, internal| ImportDescriptors {
+                descriptor: desc.to_string(),
+                timestamp: Timestamp::Time(0),
+                active: Some(true),
+                internal: Some(internal),
+                ..Default::default()
+            };
+            serde_json::to_string_pretty(&[request(external, false), request(internal, true)])?
+        }
+    })
+}
+
+/// The bip129 descriptor template, with `/**` in place of `/<a;b>/*`, and the path restrictions
+/// `/a/*,/b/*`. Every key must use the same tuple.
+fn bsms_template(descriptor: &str) -> Result<(String, String), ExportError> {
+    let multipath = Multipath::parse(descriptor).map_err(|_| ExportError::NotMultipath)?;
+    let tuple = &multipath.tuples()[0];
+    if multipath.tuples().iter().any(|t| t != tuple) {
+        return Err(ExportError::UnsupportedBsms);
+    }
+    let template = multipath.replace_tuples(|_| "**").replace("/**/*", "/**");
+    let wildcard_only = template
+        .match_indices("/**")
+        .all(|(i, m)| matches!(template[i + m.len()..].chars().next(), Some(')' | ',')));
+    if !wildcard_only {
+        return Err(ExportError::UnsupportedBsms);
+    }
+    let paths: Vec<_> = tuple.iter().map(|step| format!("/{step}/*")).collect();
+    Ok((template, paths.join(",")))
+}
+
+/// The key origin and the extended public key of a `tr` without scripts or a `wpkh` descriptor
+fn single_key(descriptor: &Descriptor) -> Option<(Fingerprint, DerivationPath, String)> {
+    let key = match descriptor {
+        Descriptor::Tr(tr) if tr.taptree().is_none() => tr.internal_key(),
+        Descriptor::Wpkh(wpkh) => wpkh.as_inner(),
+        _ => return None,
+    };
+    match key {
+        DescriptorPublicKey::MultiXPub(xpub) => {
+            let (fingerprint, path) = xpub.origin.clone()?;
+            Some((fingerprint, path, xpub.xkey.to_string()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::Network;
+
+    use super::{export, ExportError, ExportFormat};
+    use crate::Descriptor;
+
+    const DESC: &str = "tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)";
+    const MULTI: &str = "wsh(sortedmulti(2,[01e0b4da/48h/1h/0h/2h]tpubDFEFeyFLnuBLAfrgBf2LhxZAdkQNJksdWEEtdALqNA84fseVeQKULuCnxpgFsUAobzSW2HmuYrLPefYxSr1rKNwmXC84BgMdRZWLMMDK8AP/<0;1>/*,[8335dcdb/48h/1h/0h/2h]tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1/<0;1>/*))";
+
+    #[test]
+    fn test_export() {
+        let desc: Descriptor = DESC.parse().unwrap();
+        let multi: Descriptor = MULTI.parse().unwrap();
+        let export = |desc, format| export(desc, Network::Regtest, format, "heir");
+
+        let bsms = export(&multi, ExportFormat::Bsms).unwrap();
+        let lines: Vec<_> = bsms.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "BSMS 1.0");
+        assert!(lines[1].starts_with("wsh(sortedmulti(2,[01e0b4da/48'/1'/0'/2']tpub"));
+        assert_eq!(lines[1].matches("/**,").count(), 1);
+        assert_eq!(lines[2], "/0/*,/1/*");
+        assert!(lines[3].starts_with("bcrt1q"));
+
+        let specter = export(&multi, ExportFormat::Specter).unwrap();
+        assert!(specter.contains(r#""label": "heir""#));
+        assert!(specter.contains(r#""label": "8335dcdb""#));
+        assert!(specter.contains("/0/*,"));
+        let key = DESC.trim_start_matches("tr(").trim_end_matches("/<0;1>/*)");
+        let same_key: Descriptor =
+            format!("wsh(or_d(pk({key}/<0;1>/*),and_v(v:pkh({key}/<2;3>/*),older(10))))")
+                .parse()
+                .unwrap();
+        let specter = export(&same_key, ExportFormat::Specter).unwrap();
+        assert_eq!(specter.matches(r#""label": "01e0b4da""#).count(), 1);
+
+        let coldcard = export(&desc, ExportFormat::Coldcard).unwrap();
+        assert!(coldcard.contains(r#""deriv": "m/86'/1'/0'""#));
+        assert!(coldcard.contains(
+            r#""first": "bcrt1pccadr74cd29xf5y0eax2dwnfvjeqwa65c9h09f7cw6c2h6c7rjyswwdgx4""#
+        ));
+        assert!(matches!(
+            export(&multi, ExportFormat::Coldcard),
+            Err(ExportError::UnsupportedColdcard)
+        ));
+
+        let core = export(&desc, ExportFormat::Core).unwrap();
+        assert_eq!(core.matches(r#""timestamp": 0"#).count(), 2);
+        assert!(core.contains(r#""internal": true"#));
+
+        let other_tuple: Descriptor = MULTI.replace("<0;1>", "<2;3>").parse().unwrap();
+        let bsms = export(&other_tuple, ExportFormat::Bsms).unwrap();
+        assert_eq!(bsms.lines().nth(2), Some("/2/*,/3/*"));
+        assert!(export(&other_tuple, ExportFormat::Specter).is_ok());
+        let mixed: Descriptor = MULTI.replacen("<0;1>", "<2;3>", 1).parse().unwrap();
+        assert!(matches!(
+            export(&mixed, ExportFormat::Bsms),
+            Err(ExportError::UnsupportedBsms)
+        ));
+        let deeper: Descriptor = DESC.replace("<0;1>/*", "<0;1>/5/*").parse().unwrap();
+        assert!(matches!(
+            export(&deeper, ExportFormat::Bsms),
+            Err(ExportError::UnsupportedBsms)
+        ));
+
+        let single: Descriptor = DESC.replace("<0;1>", "0").parse().unwrap();
+        assert!(matches!(
+            export(&single, ExportFormat::Bsms),
+            Err(ExportError::NotMultipath)
+        ));
+    }
+}
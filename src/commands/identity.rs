@@ -1,13 +1,25 @@
-use super::Seed;
-use age::x25519::Identity;
+use super::{
+    seed::{codex32_shares, seed_combine},
+    Seed, SeedError,
+};
+use age::{secrecy::ExposeSecret, x25519::Identity};
 use bitcoin::{
-    bech32::{self, ToBase32, Variant},
-    hashes::{sha256d, Hash},
+    bech32::{self, FromBase32, ToBase32, Variant},
+    hashes::{hmac, sha256, sha256d, Hash, HashEngine},
 };
+use codex32::Codex32String;
 use std::str::FromStr;
 
 const SECRET_KEY_PREFIX: &str = "age-secret-key-";
 
+const SCRYPT_LOG_N: u8 = 17;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// Human readable part of the Codex32 shares of an identity, distinct from the `ms` of seeds and
+/// from the `age` of the public recipients, so that shares are not mistaken for recipients
+pub(crate) const IDENTITY_HRP: &str = "agesk";
+
 #[derive(thiserror::Error, Debug)]
 pub enum IdentityError {
     #[error(transparent)]
@@ -18,15 +30,100 @@ pub enum IdentityError {
 
     #[error("Identity parsing error {0}")]
     Identity(String),
+
+    #[error(transparent)]
+    Seed(#[from] SeedError),
+
+    #[error("The Codex32 shares have human readable part '{0}' instead of 'agesk'")]
+    UnexpectedHrp(String),
+
+    #[error("Invalid split '{0}', expected K-of-N like 2-of-3")]
+    InvalidSplit(String),
+}
+
+/// How many shares are created and how many of them are needed to recover the secret
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Split {
+    pub threshold: usize,
+    pub shares: usize,
+}
+
+impl FromStr for Split {
+    type Err = IdentityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (threshold, shares) = s
+            .split_once("-of-")
+            .ok_or_else(|| IdentityError::InvalidSplit(s.to_string()))?;
+        let parse = |n: &str| {
+            n.parse()
+                .map_err(|_| IdentityError::InvalidSplit(s.to_string()))
+        };
+        Ok(Split {
+            threshold: parse(threshold)?,
+            shares: parse(shares)?,
+        })
+    }
+}
+
+/// Which of the age identities of a seed
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum IdentityPath {
+    /// The identity used before the introduction of indexes and labels
+    #[default]
+    Master,
+
+    Index(u32),
+
+    Label(String),
+}
+
+/// Derive the age identity from the seed.
+///
+/// Without a bip39 passphrase the master key is the double sha256 of the mnemonic string, with the
+/// passphrase it's the double sha256 of the bip39 seed, so that it changes with the passphrase.
+///
+/// Indexed and labeled identities are the HMAC-SHA256 of the index or the label keyed with the
+/// master key, so that they are independent from each other but all restorable from the seed.
+pub fn identity(seed: &Seed, path: &IdentityPath) -> Result<Identity, IdentityError> {
+    to_identity(&identity_key(seed, path))
 }
 
-pub fn identity(seed: &Seed) -> Result<Identity, IdentityError> {
+/// Like [`identity`] but the key is further derived with scrypt and the given passphrase, so that
+/// the seed alone is not enough to rebuild the identity.
+///
+/// The key derived by [`identity`] is used as salt. Scrypt parameters are fixed, since changing
+/// them changes the identity: `log_n` 17, `r` 8 and `p` 1, requiring 128MiB of memory.
+pub fn hardened_identity(
+    seed: &Seed,
+    path: &IdentityPath,
+    passphrase: &str,
+) -> Result<Identity, IdentityError> {
+    let salt = identity_key(seed, path);
+    let params =
+        scrypt::Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P).expect("valid constant parameters");
+    let mut key = [0u8; 32];
+    scrypt::scrypt(passphrase.as_bytes(), &salt, &params, &mut key).expect("valid output length");
+    to_identity(&key)
+}
+
+fn identity_key(seed: &Seed, path: &IdentityPath) -> Vec<u8> {
     let mnemonic = seed.mnemonic();
-    let hash = sha256d::Hash::hash(mnemonic.to_string().as_bytes())
-        .as_byte_array()
-        .to_vec();
+    let master = if seed.passphrase().is_empty() {
+        sha256d::Hash::hash(mnemonic.to_string().as_bytes())
+    } else {
+        sha256d::Hash::hash(&mnemonic.to_seed(seed.passphrase()))
+    };
 
-    let encoded = bech32::encode(SECRET_KEY_PREFIX, hash.to_base32(), Variant::Bech32)?;
+    match path {
+        IdentityPath::Master => master.to_byte_array().to_vec(),
+        IdentityPath::Index(index) => child(&master, b"index", &index.to_be_bytes()),
+        IdentityPath::Label(label) => child(&master, b"label", label.as_bytes()),
+    }
+}
+
+fn to_identity(key: &[u8]) -> Result<Identity, IdentityError> {
+    let encoded = bech32::encode(SECRET_KEY_PREFIX, key.to_base32(), Variant::Bech32)?;
 
     let identity =
         Identity::from_str(&encoded).map_err(|e| IdentityError::Identity(e.to_string()))?;
@@ -34,9 +131,64 @@ pub fn identity(seed: &Seed) -> Result<Identity, IdentityError> {
     Ok(identity)
 }
 
+/// Split the identity secret key in Codex32 shares with human readable part `agesk`, so that the
+/// identity could be given to trustees and any `threshold` of them are needed to rebuild it.
+///
+/// The identifier of the shares is taken from the public recipient, so that shares could be
+/// matched with it.
+pub fn identity_shares(
+    identity: &Identity,
+    split: Split,
+) -> Result<Vec<Codex32String>, IdentityError> {
+    let (_, data, _) = bech32::decode(identity.to_string().expose_secret())?;
+    let secret = Vec::<u8>::from_base32(&data)?;
+    let recipient = identity.to_public().to_string();
+    let id = &recipient["age1".len().."age1".len() + 4];
+
+    Ok(codex32_shares(
+        IDENTITY_HRP,
+        &secret,
+        id,
+        split.threshold,
+        split.shares,
+    )?)
+}
+
+/// Rebuild the identity from at least `threshold` of the Codex32 shares created by
+/// [`identity_shares`]
+pub fn identity_combine(shares: &[String]) -> Result<Identity, IdentityError> {
+    let secret = seed_combine(shares)?;
+    let secret = secret.to_string().to_ascii_lowercase();
+    let (hrp, _) = secret
+        .rsplit_once('1')
+        .expect("valid codex32 contains separator");
+    if hrp != IDENTITY_HRP {
+        return Err(IdentityError::UnexpectedHrp(hrp.to_string()));
+    }
+    let data = Codex32String::from_string(secret)
+        .expect("valid codex32")
+        .parts()
+        .data();
+
+    to_identity(&data)
+}
+
+fn child(master: &sha256d::Hash, kind: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut engine = hmac::HmacEngine::<sha256::Hash>::new(master.as_byte_array());
+    engine.input(b"dinasty age identity ");
+    engine.input(kind);
+    engine.input(b":");
+    engine.input(data);
+    hmac::Hmac::<sha256::Hash>::from_engine(engine)
+        .to_byte_array()
+        .to_vec()
+}
+
 #[cfg(test)]
 mod test {
-    use super::identity;
+    use super::{
+        hardened_identity, identity, identity_combine, identity_shares, IdentityError, IdentityPath,
+    };
     use crate::commands::Seed;
     use age::secrecy::ExposeSecret;
     use std::str::FromStr;
@@ -50,7 +202,7 @@ mod test {
 
         assert_eq!(seed.fingerprint().unwrap().to_string(), "8335dcdb");
 
-        let id = identity(&seed).unwrap();
+        let id = identity(&seed, &IdentityPath::Master).unwrap();
 
         assert_eq!(
             id.to_string().expose_secret(),
@@ -61,5 +213,94 @@ mod test {
             id.to_public().to_string(),
             "age1qqly9jy2g3gfykzdnnegrjg3zpcsd086ckcllzlppaqw59puxy7q4a4asf"
         );
+
+        let ids: Vec<_> = [
+            IdentityPath::Index(0),
+            IdentityPath::Index(1),
+            IdentityPath::Label("alice".to_string()),
+            IdentityPath::Label("bob".to_string()),
+        ]
+        .iter()
+        .map(|path| identity(&seed, path).unwrap().to_public().to_string())
+        .collect();
+        assert!(ids.iter().all(|i| *i != id.to_public().to_string()));
+        for (i, a) in ids.iter().enumerate() {
+            assert!(ids[i + 1..].iter().all(|b| a != b));
+        }
+        assert_eq!(
+            ids[0],
+            identity(&seed, &IdentityPath::Index(0))
+                .unwrap()
+                .to_public()
+                .to_string()
+        );
+
+        let seed = seed.with_passphrase("secret");
+        let id_passphrase = identity(&seed, &IdentityPath::Master).unwrap();
+        assert_ne!(
+            id.to_public().to_string(),
+            id_passphrase.to_public().to_string()
+        );
+    }
+
+    #[test]
+    fn test_identity_split() {
+        let seed = Seed::from_str(
+            "alter trial legal chuckle wear mansion sweet invest shy cabin autumn ribbon",
+        )
+        .unwrap();
+        let id = identity(&seed, &IdentityPath::Master).unwrap();
+
+        let split = "2-of-3".parse().unwrap();
+        let shares: Vec<_> = identity_shares(&id, split)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(shares.len(), 3);
+        assert!(shares.iter().all(|s| s.starts_with("agesk12qqly")));
+
+        for pair in [[0, 1], [1, 2], [2, 0]] {
+            let selected = [shares[pair[0]].clone(), shares[pair[1]].clone()];
+            let combined = identity_combine(&selected).unwrap();
+            assert_eq!(
+                combined.to_string().expose_secret(),
+                id.to_string().expose_secret()
+            );
+        }
+
+        assert!(identity_combine(&shares[..1]).is_err());
+        assert!(matches!(
+            Seed::from_str(&shares[..2].join("\n")),
+            Err(crate::commands::SeedError::UnexpectedHrp(_))
+        ));
+        assert!(matches!(
+            "2of3".parse::<super::Split>(),
+            Err(IdentityError::InvalidSplit(_))
+        ));
+    }
+
+    #[test]
+    fn test_hardened_identity() {
+        let seed = Seed::from_str(
+            "alter trial legal chuckle wear mansion sweet invest shy cabin autumn ribbon",
+        )
+        .unwrap();
+        let id = identity(&seed, &IdentityPath::Master).unwrap();
+        let hardened = hardened_identity(&seed, &IdentityPath::Master, "kdf secret").unwrap();
+        let other = hardened_identity(&seed, &IdentityPath::Master, "other").unwrap();
+        let indexed = hardened_identity(&seed, &IdentityPath::Index(0), "kdf secret").unwrap();
+
+        let recipients: Vec<_> = [&id, &hardened, &other, &indexed]
+            .iter()
+            .map(|i| i.to_public().to_string())
+            .collect();
+        for (i, a) in recipients.iter().enumerate() {
+            assert!(recipients[i + 1..].iter().all(|b| a != b));
+        }
+        assert_eq!(
+            hardened.to_public().to_string(),
+            "age156mc5ayyew0fgz8u0ea7cfxtpctezhdsrfy8gwjprumf0aj7f9mqpvksx0"
+        );
     }
 }
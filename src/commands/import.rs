@@ -1,10 +1,31 @@
+use std::{
+    str::FromStr,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::{without_checksum, ImportOptions};
 use crate::client_ext::ClientExt;
 use crate::core_connect::CoreConnect;
+use crate::Descriptor;
+use bitcoin::bip32::ChildNumber;
 use bitcoin::secp256k1::Secp256k1;
 use bitcoind::bitcoincore_rpc;
+use bitcoind::bitcoincore_rpc::core_rpc_json::{ScanningDetails, Timestamp};
 use bitcoind::bitcoincore_rpc::jsonrpc::serde_json;
+use bitcoind::bitcoincore_rpc::{Client, RpcApi};
+
+/// Values below are block heights, above unix timestamps, like `nLockTime`
+const LOCKTIME_THRESHOLD: u64 = 500_000_000;
+
+/// The `importdescriptors` call returns only when the rescan is done, which could take hours
+const RESCAN_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Importing without rescan doesn't take long, the wallet is locked again anyway after the import
+const UNLOCK_TIMEOUT: Duration = Duration::from_secs(10);
 
-const MULTIPATH: &str = "<0;1>";
+/// How often the rescan progress is polled while importing
+const RESCAN_POLL: Duration = Duration::from_secs(1);
 
 #[derive(thiserror::Error, Debug)]
 pub enum ImportError {
@@ -20,9 +41,23 @@ pub enum ImportError {
     #[error(transparent)]
     Miniscript(#[from] miniscript::Error),
 
-    #[error("Given descriptor isn't multipat, it doesn't contain <0;1>")]
+    #[error("Given descriptor isn't multipath, it doesn't contain a tuple like <0;1>")]
     DescriptorIsntMultipath,
 
+    #[error("Invalid multipath tuple <{0}>, it must contain 2 or more distinct derivation steps")]
+    InvalidMultipath(String),
+
+    #[error("A multipath tuple in the descriptor is not closed by '>'")]
+    UnclosedMultipath,
+
+    #[error("The multipath tuples in the descriptor have different lengths")]
+    MultipathLengthMismatch,
+
+    #[error(
+        "The multipath tuples must have 2 elements, one for receive and one for change, found {0}"
+    )]
+    NotReceiveAndChange(usize),
+
     #[error("With private key flags used but descriptors don't contain private keys")]
     WithPrivateKeyFlagButPublicDescriptor,
 
@@ -31,39 +66,246 @@ pub enum ImportError {
 
     #[error("Without private key flags used but descriptors contain private keys")]
     CannotImport,
+
+    #[error("Invalid birthday '{0}', expected a past unix timestamp, a block height or a date like 2023-12-31")]
+    InvalidBirthday(String),
+}
+
+/// Since when the wallet could have received funds, the rescan of the chain starts from here
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Birthday {
+    /// Unix timestamp in seconds
+    Time(u64),
+
+    /// Block height, the rescan starts from the time of the block
+    Height(u64),
+}
+
+impl Birthday {
+    /// The birthday of a wallet that could have funds since the genesis block
+    pub const GENESIS: Birthday = Birthday::Time(0);
+
+    fn timestamp(&self, client: &Client) -> Result<Timestamp, ImportError> {
+        Ok(match self {
+            Birthday::Time(time) => Timestamp::Time(*time),
+            Birthday::Height(height) => {
+                let hash = client.get_block_hash(*height)?;
+                let header = client.get_block_header_info(&hash)?;
+                Timestamp::Time(header.time as u64)
+            }
+        })
+    }
+}
+
+/// Parse a unix timestamp, a block height or a UTC date like `2023-12-31`
+impl FromStr for Birthday {
+    type Err = ImportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ImportError::InvalidBirthday(s.to_string());
+        let s = s.trim();
+        // a birthday in the future is surely a typo, the wallet couldn't have funds anyway
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock after 1970")
+            .as_secs();
+        if let Ok(number) = s.parse::<u64>() {
+            return match number {
+                n if n < LOCKTIME_THRESHOLD => Ok(Birthday::Height(n)),
+                n if n <= now => Ok(Birthday::Time(n)),
+                _ => Err(invalid()),
+            };
+        }
+
+        let parts: Vec<u64> = s
+            .split('-')
+            .map(|p| p.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        let [year, month, day] = parts[..] else {
+            return Err(invalid());
+        };
+        if year < 1970
+            || !(1..=12).contains(&month)
+            || !(1..=days_in_month(year, month)).contains(&day)
+        {
+            return Err(invalid());
+        }
+        let time = days_from_civil(year, month, day)
+            .and_then(|days| days.checked_mul(24 * 60 * 60))
+            .filter(|time| *time <= now)
+            .ok_or_else(invalid)?;
+        Ok(Birthday::Time(time))
+    }
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of the given valid date of the proleptic gregorian calendar, from 1970,
+/// `None` if it overflows
+fn days_from_civil(year: u64, month: u64, day: u64) -> Option<u64> {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era.checked_mul(146097)?
+        .checked_add(day_of_era)?
+        .checked_sub(719468)
 }
 
 /// Import descriptors into bitcoin core, note we are explicitly using string because multipath secret descriptor are not supported
+///
+/// Without a birthday only new transactions are found, otherwise the chain is rescanned from
+/// the birthday, logging the progress, so that existing funds are found too.
+///
+/// If `existing`, the wallet is loaded or created if needed and only the descriptors not already
+/// in the wallet are imported, so that the import could be repeated after a partial failure.
 pub fn import(
     core_connect: &CoreConnect,
     desc: &str,
     wallet_name: &str,
-    with_private_keys: bool,
+    options: &ImportOptions,
 ) -> Result<String, ImportError> {
+    let ImportOptions {
+        with_private_keys,
+        existing,
+        ..
+    } = *options;
+    let birthday = options.birthday();
     let client = core_connect.client()?;
 
     let ExplodedDesc { internal, external } = explode_descriptor(desc, with_private_keys)?;
     let internal = client.add_checksum(&internal)?;
     let external = client.add_checksum(&external)?;
 
-    let client = client.create_blank_wallet(
-        wallet_name,
-        core_connect,
-        !with_private_keys,
-        with_private_keys.then(|| desc),
-    )?;
-    if with_private_keys {
-        client.wallet_passphrase(&desc)
+    let client = if existing && client.list_wallets()?.iter().any(|w| w == wallet_name) {
+        core_connect.client_with_wallet(wallet_name)?
+    } else if existing && client.list_wallet_dir()?.iter().any(|w| w == wallet_name) {
+        client.load_wallet(wallet_name)?;
+        core_connect.client_with_wallet(wallet_name)?
+    } else {
+        client.create_blank_wallet(
+            wallet_name,
+            core_connect,
+            !with_private_keys,
+            with_private_keys.then_some(desc),
+        )?
+    };
+
+    let mut descriptors = vec![(external.as_str(), false), (internal.as_str(), true)];
+    if existing {
+        let present = wallet_descriptors(&client, wallet_name)?;
+        let secp = Secp256k1::new();
+        let mut missing = vec![];
+        for (descriptor, internal) in descriptors {
+            let (public, _) = Descriptor::parse_descriptor(&secp, descriptor)?;
+            if !present.contains(&public) {
+                missing.push((descriptor, internal));
+            }
+        }
+        descriptors = missing;
+    }
+    let skipped = 2 - descriptors.len();
+    if descriptors.is_empty() {
+        return Ok(format!("ok, 0 imported, {skipped} already in the wallet"));
     }
 
-    let r1 = client.import_descriptor(&external, false)?;
-    let r2 = client.import_descriptor(&internal, true)?;
+    let timestamp = match birthday {
+        Some(birthday) => birthday.timestamp(&client)?,
+        None => Timestamp::Now,
+    };
+
+    // a new signer wallet is always encrypted, an existing one could be not
+    let unlock =
+        with_private_keys && (!existing || client.get_wallet_info()?.unlocked_until.is_some());
+    if unlock {
+        let timeout = match birthday {
+            Some(_) => RESCAN_TIMEOUT,
+            None => UNLOCK_TIMEOUT,
+        };
+        client.wallet_passphrase(desc, timeout)?;
+    }
 
-    if !r1.success || !r2.success {
+    let results = match birthday {
+        None => client.import_active_descriptors(&descriptors, timestamp),
+        Some(_) => {
+            let import_client =
+                core_connect.client_with_wallet_timeout(wallet_name, RESCAN_TIMEOUT)?;
+            let start = Instant::now();
+            let results = thread::scope(|s| {
+                let handle =
+                    s.spawn(|| import_client.import_active_descriptors(&descriptors, timestamp));
+                log_rescan_progress(&client, || handle.is_finished());
+                handle.join().expect("import thread doesn't panic")
+            });
+            if results.is_ok() {
+                log::info!("Rescan done in {} seconds", start.elapsed().as_secs());
+            }
+            results
+        }
+    };
+
+    // the unlock timeout covers the longest rescan, don't leave the wallet unlocked that long
+    let locked = if unlock { client.wallet_lock() } else { Ok(()) };
+    let results = results?;
+    locked?;
+
+    if results.len() != descriptors.len() || results.iter().any(|r| !r.success) {
         return Err(ImportError::CannotImport);
     }
 
-    Ok("ok".to_string())
+    Ok(if existing {
+        format!(
+            "ok, {} imported, {skipped} already in the wallet",
+            results.len()
+        )
+    } else {
+        "ok".to_string()
+    })
+}
+
+/// The descriptors of the wallet, private keys if any aren't returned
+fn wallet_descriptors(client: &Client, wallet_name: &str) -> Result<Vec<Descriptor>, ImportError> {
+    client
+        .list_descriptors(wallet_name)?
+        .iter()
+        .map(|d| Ok(Descriptor::from_str(&d.desc)?))
+        .collect()
+}
+
+/// Until `finished`, poll the wallet and log the rescan progress when it changes by at least 1%,
+/// a failing poll is logged once, the import goes on anyway
+fn log_rescan_progress(client: &Client, finished: impl Fn() -> bool) {
+    let mut last_percent = None;
+    let mut warned = false;
+    while !finished() {
+        thread::sleep(RESCAN_POLL);
+        let info = match client.get_wallet_info() {
+            Ok(info) => info,
+            Err(e) => {
+                if !warned {
+                    log::warn!("Cannot get the rescan progress: {e}");
+                    warned = true;
+                }
+                continue;
+            }
+        };
+        if let Some(ScanningDetails::Scanning { duration, progress }) = info.scanning {
+            let percent = (progress * 100.0) as u32;
+            if last_percent != Some(percent) {
+                log::info!("Rescanning the chain, {percent}% done in {duration} seconds");
+                last_percent = Some(percent);
+            }
+        }
+    }
 }
 
 pub(crate) struct ExplodedDesc {
@@ -73,19 +315,20 @@ pub(crate) struct ExplodedDesc {
     /// /0/
     external: String,
 }
-/// from a descriptor with the "<0;1>" notation creates 2 descriptors, one with 0 and the other with 1
+/// From a multipath descriptor like `/<0;1>/*` creates 2 descriptors, the external with the
+/// first element of every tuple and the internal with the second.
 /// This shouldn't be used outside of this module, here is used because multipath secret descriptor
 /// aren't supported in rust-miniscript
 pub(crate) fn explode_descriptor(
     desc: &str,
     with_private_keys: bool,
 ) -> Result<ExplodedDesc, ImportError> {
-    if !desc.contains(MULTIPATH) {
-        return Err(ImportError::DescriptorIsntMultipath);
-    }
-    let (external, internal) = (desc.replace(MULTIPATH, "0"), desc.replace(MULTIPATH, "1"));
+    let singles = split_multipath(desc)?;
+    let [external, internal] = <[String; 2]>::try_from(singles)
+        .map_err(|singles| ImportError::NotReceiveAndChange(singles.len()))?;
     let secp = Secp256k1::new();
-    let (_, key_map) = miniscript::Descriptor::parse_descriptor(&secp, &external)?; // I can check only external or internal since they are the same but the change index
+    let (_, key_map) = miniscript::Descriptor::parse_descriptor(&secp, &external)?;
+    let _ = miniscript::Descriptor::parse_descriptor(&secp, &internal)?;
 
     if with_private_keys && key_map.is_empty() {
         return Err(ImportError::WithPrivateKeyFlagButPublicDescriptor);
@@ -97,6 +340,139 @@ pub(crate) fn explode_descriptor(
     Ok(ExplodedDesc { internal, external })
 }
 
+/// Split a descriptor containing multipath tuples like `<0;1>` or `<2;3;4>` in the single path
+/// descriptors, the i-th one has every tuple replaced by its i-th element, as specified in bip389.
+///
+/// It works on the string so that descriptors with private keys are supported too. All the tuples
+/// must have the same length. The checksum, if any, is dropped since it would be invalid.
+pub(crate) fn split_multipath(desc: &str) -> Result<Vec<String>, ImportError> {
+    Ok(Multipath::parse(desc)?.singles())
+}
+
+/// Parse the public descriptor given on the command line, validating its multipath tuples like
+/// [`split_multipath`] first, since miniscript silently merges repeated steps like `<1;1>`
+pub(crate) fn parse_descriptor(s: &str) -> Result<Descriptor, ImportError> {
+    match Multipath::parse(s) {
+        Ok(_) | Err(ImportError::DescriptorIsntMultipath) => Ok(Descriptor::from_str(s.trim())?),
+        Err(e) => Err(e),
+    }
+}
+
+/// The single path descriptors of the public `descriptor`, split like [`split_multipath`] so that
+/// every command validates the tuples the same way. A descriptor without tuples is returned as is.
+pub(crate) fn single_descriptors(descriptor: &Descriptor) -> Result<Vec<Descriptor>, ImportError> {
+    match split_multipath(&descriptor.to_string()) {
+        Ok(singles) => singles
+            .iter()
+            .map(|single| Ok(Descriptor::from_str(single)?))
+            .collect(),
+        Err(ImportError::DescriptorIsntMultipath) => Ok(vec![descriptor.clone()]),
+        Err(e) => Err(e),
+    }
+}
+
+/// A descriptor split around its multipath tuples, the checksum excluded
+pub(crate) struct Multipath<'a> {
+    /// The text preceding every tuple
+    parts: Vec<&'a str>,
+
+    /// The elements of every tuple, like `["0", "1"]` for `<0;1>`
+    tuples: Vec<Vec<&'a str>>,
+
+    /// The text following the last tuple
+    rest: &'a str,
+}
+
+impl<'a> Multipath<'a> {
+    /// Parse the multipath tuples of `desc`, every tuple must contain 2 or more distinct
+    /// derivation steps and all the tuples must have the same length.
+    pub(crate) fn parse(desc: &'a str) -> Result<Self, ImportError> {
+        let desc = without_checksum(desc.trim());
+
+        let mut parts = vec![];
+        let mut tuples: Vec<Vec<&str>> = vec![];
+        let mut rest = desc;
+        while let Some(start) = rest.find('<') {
+            let end = start
+                + rest[start..]
+                    .find('>')
+                    .ok_or(ImportError::UnclosedMultipath)?;
+            let content = &rest[start + 1..end];
+            let tuple: Vec<&str> = content.split(';').collect();
+            let steps: Option<Vec<ChildNumber>> = tuple.iter().map(|s| parse_step(s)).collect();
+            let distinct = steps.map_or(false, |steps| {
+                steps
+                    .iter()
+                    .enumerate()
+                    .all(|(i, a)| !steps[i + 1..].contains(a))
+            });
+            if tuple.len() < 2 || !distinct {
+                return Err(ImportError::InvalidMultipath(content.to_string()));
+            }
+            parts.push(&rest[..start]);
+            tuples.push(tuple);
+            rest = &rest[end + 1..];
+        }
+
+        let len = tuples
+            .first()
+            .ok_or(ImportError::DescriptorIsntMultipath)?
+            .len();
+        if tuples.iter().any(|t| t.len() != len) {
+            return Err(ImportError::MultipathLengthMismatch);
+        }
+
+        Ok(Self {
+            parts,
+            tuples,
+            rest,
+        })
+    }
+
+    /// The tuples in order of appearance
+    pub(crate) fn tuples(&self) -> &[Vec<&'a str>] {
+        &self.tuples
+    }
+
+    /// The single path descriptors, one for every element of the tuples
+    pub(crate) fn singles(&self) -> Vec<String> {
+        (0..self.tuples[0].len())
+            .map(|i| self.replace_tuples(|tuple| tuple[i]))
+            .collect()
+    }
+
+    /// The descriptor with every tuple, brackets included, replaced by `with(tuple)`
+    pub(crate) fn replace_tuples<'s>(&self, with: impl Fn(&[&'a str]) -> &'s str) -> String {
+        let mut replaced = String::new();
+        for (part, tuple) in self.parts.iter().zip(self.tuples.iter()) {
+            replaced.push_str(part);
+            replaced.push_str(with(tuple));
+        }
+        replaced.push_str(self.rest);
+        replaced
+    }
+}
+
+/// A derivation step like `1`, `1h` or `1'`, so that equal steps compare equal whatever the
+/// notation. Leading signs, spaces and repeated hardened markers are rejected.
+fn parse_step(step: &str) -> Option<ChildNumber> {
+    let index = step.trim_end_matches(['h', 'H', '\'']);
+    let hardened = match step.len() - index.len() {
+        0 => false,
+        1 => true,
+        _ => return None,
+    };
+    if index.is_empty() || !index.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let index = index.parse().ok()?;
+    if hardened {
+        ChildNumber::from_hardened_idx(index).ok()
+    } else {
+        ChildNumber::from_normal_idx(index).ok()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
@@ -106,6 +482,8 @@ mod test {
     };
     use bitcoind::bitcoincore_rpc::RpcApi;
 
+    use super::ImportOptions;
+
     #[test]
     fn test_import() {
         let TestNode {
@@ -113,12 +491,30 @@ mod test {
         } = crate::test_util::setup_node();
         let desc = "tr([8335dcdb/48'/1'/0'/2']tprv8ifUoGVh57yDBkyW2sS6kMNv7ewZVLmSLp1RSgZw4H5AhMP6AtxJB1P842vZcvdu9giYEfWDa6NX5nCGaaUVK5boJt1AeA8fFKv2u87Ua3g/<0;1>/*)";
 
-        let _ = commands::import(&core_connect, desc, "1", true).unwrap();
-        let _ = commands::import(&core_connect, desc, "2", false).unwrap_err();
+        let _ = commands::import(
+            &core_connect,
+            desc,
+            "1",
+            &ImportOptions {
+                with_private_keys: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let _ = commands::import(&core_connect, desc, "2", &Default::default()).unwrap_err();
 
         let desc: &str = "tr([8335dcdb/48'/1'/0'/2']tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1/<0;1>/*)";
-        let _ = commands::import(&core_connect, desc, "3", false).unwrap();
-        let _ = commands::import(&core_connect, desc, "4", true).unwrap_err();
+        let _ = commands::import(&core_connect, desc, "3", &Default::default()).unwrap();
+        let _ = commands::import(
+            &core_connect,
+            desc,
+            "4",
+            &ImportOptions {
+                with_private_keys: true,
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
 
         let wallets = node.client.list_wallets().unwrap();
 
@@ -128,6 +524,220 @@ mod test {
         assert!(!wallets.contains(&"4".to_owned()));
     }
 
+    #[test]
+    fn test_import_birthday() {
+        use super::Birthday;
+        use crate::test_util::TestEnv;
+
+        let TestEnv { core_connect, .. } = crate::test_util::setup_node_and_wallets();
+        let desc = "tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)";
+        let balance = |wallet_name| {
+            let client = core_connect.client_with_wallet(wallet_name).unwrap();
+            let mine = client.get_balances().unwrap().mine;
+            (mine.trusted + mine.immature).to_sat()
+        };
+
+        commands::import(&core_connect, desc, "now", &Default::default()).unwrap();
+        assert_eq!(balance("now"), 0);
+
+        let funded = balance("watch_only");
+        assert!(funded > 0);
+        for (wallet_name, birthday) in [
+            ("genesis", Birthday::GENESIS),
+            ("height", Birthday::Height(1)),
+        ] {
+            let options = ImportOptions {
+                birthday: Some(birthday),
+                ..Default::default()
+            };
+            commands::import(&core_connect, desc, wallet_name, &options).unwrap();
+            assert_eq!(balance(wallet_name), funded);
+        }
+    }
+
+    #[test]
+    fn test_import_existing() {
+        use super::{explode_descriptor, ExplodedDesc};
+        use crate::client_ext::ClientExt;
+
+        let TestNode {
+            node, core_connect, ..
+        } = crate::test_util::setup_node();
+        let xprv_desc = "tr([01e0b4da/86h/1h/0h]tprv8fXspLN8b22B19ViogBWdGHR4ZHkoUd7VvMpoUZCkPZtHiKLZyc9H9pgfTnZwrosXQ5hKLTdSCPerVrgtewQjTSRy1YEngEZXHNCvTodhtz/<0;1>/*)";
+        let xpub_desc = "tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)";
+        let import = |desc, wallet_name, with_private_keys| {
+            let options = ImportOptions {
+                with_private_keys,
+                existing: true,
+                ..Default::default()
+            };
+            commands::import(&core_connect, desc, wallet_name, &options).unwrap()
+        };
+
+        let all = "ok, 2 imported, 0 already in the wallet";
+        let none = "ok, 0 imported, 2 already in the wallet";
+        assert_eq!(import(xprv_desc, "signer", true), all);
+        assert_eq!(import(xprv_desc, "signer", true), none);
+        commands::import(
+            &core_connect,
+            xprv_desc,
+            "signer",
+            &ImportOptions {
+                with_private_keys: true,
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+
+        node.client.unload_wallet(Some("signer")).unwrap();
+        assert_eq!(import(xprv_desc, "signer", true), none);
+
+        // partial failure after the wallet creation and the first import
+        let ExplodedDesc { external, .. } = explode_descriptor(xpub_desc, false).unwrap();
+        let client = node
+            .client
+            .create_blank_wallet("watch_only", &core_connect, true, None)
+            .unwrap();
+        client
+            .import_descriptor(&client.add_checksum(&external).unwrap(), false)
+            .unwrap();
+        assert_eq!(
+            import(xpub_desc, "watch_only", false),
+            "ok, 1 imported, 1 already in the wallet"
+        );
+
+        // locked signer created with the private descriptor as passphrase
+        node.client
+            .create_blank_wallet("locked", &core_connect, false, Some(xprv_desc))
+            .unwrap();
+        assert_eq!(import(xprv_desc, "locked", true), all);
+        let locked = core_connect.client_with_wallet("locked").unwrap();
+        assert_eq!(locked.get_wallet_info().unwrap().unlocked_until, Some(0));
+
+        let existing_signer = ImportOptions {
+            with_private_keys: true,
+            existing: true,
+            ..Default::default()
+        };
+        node.client
+            .create_blank_wallet("other", &core_connect, false, Some("other passphrase"))
+            .unwrap();
+        assert!(matches!(
+            commands::import(&core_connect, xprv_desc, "other", &existing_signer),
+            Err(super::ImportError::CoreRpc(_))
+        ));
+    }
+
+    #[test]
+    fn test_birthday() {
+        use super::{Birthday, ImportError};
+
+        let parse = |s: &str| s.parse::<Birthday>();
+        assert_eq!(parse("0").unwrap(), Birthday::Height(0));
+        assert_eq!(parse("840000").unwrap(), Birthday::Height(840000));
+        assert_eq!(parse("1700000000").unwrap(), Birthday::Time(1700000000));
+        assert_eq!(parse("1970-01-01").unwrap(), Birthday::Time(0));
+        assert_eq!(parse("2009-01-03").unwrap(), Birthday::Time(1230940800));
+        assert_eq!(parse("2024-02-29").unwrap(), Birthday::Time(1709164800));
+        assert_eq!(parse("2024-03-01").unwrap(), Birthday::Time(1709251200));
+        assert_eq!(parse("2000-02-29").unwrap(), Birthday::Time(951782400));
+        assert_eq!(parse("2023-04-30").unwrap(), Birthday::Time(1682812800));
+
+        for invalid in [
+            "",
+            "-1",
+            "yesterday",
+            "2024-13-01",
+            "2024-01-00",
+            "2023-02-29",
+            "2023-02-31",
+            "2023-04-31",
+            "1900-02-29",
+            "1969-12-31",
+            "2024-01",
+            "99999999999-01-01",
+            "9999-12-31",
+            "99999999999",
+            "18446744073709551615-12-31",
+        ] {
+            assert!(matches!(
+                parse(invalid),
+                Err(ImportError::InvalidBirthday(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_split_multipath() {
+        use std::str::FromStr;
+
+        use super::{
+            explode_descriptor, parse_descriptor, single_descriptors, split_multipath, ImportError,
+        };
+
+        let xprv = "tprv8ifUoGVh57yDBkyW2sS6kMNv7ewZVLmSLp1RSgZw4H5AhMP6AtxJB1P842vZcvdu9giYEfWDa6NX5nCGaaUVK5boJt1AeA8fFKv2u87Ua3g";
+        let xpub = "tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1";
+
+        let desc = format!("wsh(or_d(pk({xprv}/<0;1>/*),and_v(v:pkh({xpub}/<2;3>/*),older(10))))");
+        let singles = split_multipath(&format!("{desc}#checksum")).unwrap();
+        assert_eq!(
+            singles,
+            vec![
+                format!("wsh(or_d(pk({xprv}/0/*),and_v(v:pkh({xpub}/2/*),older(10))))"),
+                format!("wsh(or_d(pk({xprv}/1/*),and_v(v:pkh({xpub}/3/*),older(10))))"),
+            ]
+        );
+        let exploded = explode_descriptor(&desc, true).unwrap();
+        assert_eq!(exploded.external, singles[0]);
+        assert_eq!(exploded.internal, singles[1]);
+
+        let three = split_multipath(&format!("tr({xpub}/<0h;1';2>/*)")).unwrap();
+        assert_eq!(three.len(), 3);
+        assert_eq!(three[1], format!("tr({xpub}/1'/*)"));
+        assert!(matches!(
+            explode_descriptor(&format!("tr({xpub}/<0;1;2>/*)"), false),
+            Err(ImportError::NotReceiveAndChange(3))
+        ));
+
+        let err = |desc: String| split_multipath(&desc).unwrap_err();
+        assert!(matches!(
+            err(format!("tr({xpub}/0/*)")),
+            ImportError::DescriptorIsntMultipath
+        ));
+        assert!(matches!(
+            err(format!("wsh(multi(1,{xpub}/<0;1>/*,{xprv}/<0;1;2>/*))")),
+            ImportError::MultipathLengthMismatch
+        ));
+        for tuple in [
+            "<0>", "<0;0>", "<0;x>", "<;1>", "<0hh;1>", "<1h;1'>", "<0;00>", "<+0;1>",
+        ] {
+            assert!(matches!(
+                err(format!("tr({xpub}/{tuple}/*)")),
+                ImportError::InvalidMultipath(_)
+            ));
+        }
+        assert!(matches!(
+            err(format!("tr({xprv}/<0;1/*)")),
+            ImportError::UnclosedMultipath
+        ));
+
+        let parsed = parse_descriptor(&format!("tr({xpub}/<0;1>/*)")).unwrap();
+        let singles = single_descriptors(&parsed).unwrap();
+        assert_eq!(singles.len(), 2);
+        assert_eq!(
+            singles[1].to_string(),
+            format!("{}", Descriptor::from_str(&format!("tr({xpub}/1/*)")).unwrap())
+        );
+        let single = parse_descriptor(&format!("tr({xpub}/0/*)")).unwrap();
+        assert_eq!(single_descriptors(&single).unwrap(), vec![single]);
+        for repeated in ["<1;1>", "<1h;1'>", "<0;1;0>"] {
+            assert!(matches!(
+                parse_descriptor(&format!("tr({xpub}/{repeated}/*)")),
+                Err(ImportError::InvalidMultipath(_))
+            ));
+        }
+    }
+
     #[test]
     fn test_multipath() {
         let desc: &str = "tr([8335dcdb/48'/1'/0'/2']tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1/<0;1>/*)";
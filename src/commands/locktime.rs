@@ -122,7 +122,7 @@ pub fn locktime(
         };
 
         let blockchain_info = client_from.get_blockchain_info()?;
-        if blockchain_info.initial_block_download == true {
+        if blockchain_info.initial_block_download {
             return Err(LocktimeError::StillIBD);
         }
         let locktime = blockchain_info.blocks as i64 + locktime_future;
@@ -164,10 +164,19 @@ mod test {
 
         let heir_wo_desc = "tr([01e0b4da/1']tpubD8GvnJ7jbLd3ZCmUUoTwDMpQ5N7sVv2HjW4sBgBss7zeEm8mPPSxDmDxYy4rxGZbQAcbRGwawzXMUpnLAnHcrNmZcqucy3qAyn7NZzKChpx/<0;1>/*)";
 
-        commands::import(&core_connect, owner_wo_desc, "wo", false).unwrap();
-        commands::import(&core_connect, owner_desc, "signer", true).unwrap();
-
-        commands::import(&core_connect, heir_wo_desc, "heir", false).unwrap();
+        commands::import(&core_connect, owner_wo_desc, "wo", &Default::default()).unwrap();
+        commands::import(
+            &core_connect,
+            owner_desc,
+            "signer",
+            &commands::ImportOptions {
+                with_private_keys: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        commands::import(&core_connect, heir_wo_desc, "heir", &Default::default()).unwrap();
 
         let wo_client = core_connect.client_with_wallet("wo").unwrap();
         let signer_client = core_connect.client_with_wallet("signer").unwrap();
@@ -1,6 +1,13 @@
+mod account_xpub;
+mod addresses;
+mod bip85;
 mod broadcast;
+mod correction;
 mod descriptor;
 mod details;
+mod encrypt;
+mod entropy;
+mod export;
 mod identity;
 mod import;
 mod locktime;
@@ -8,21 +15,39 @@ mod qr;
 mod refresh;
 mod seed;
 mod sign;
+mod verify_address;
+mod verify_backup;
+mod wallet_policy;
+mod worksheet;
 
+use bitcoin::{address::NetworkUnchecked, Address};
 use clap::{Args, Subcommand};
 use clap_complete::Shell;
 use std::{net::SocketAddrV4, path::PathBuf};
 
+pub use account_xpub::{account_xpub, account_xpub_ur, AccountXpubError, XpubFormat};
+pub use addresses::{addresses, AddressesError};
+pub use bip85::{bip85, Bip85Application, Bip85Error};
 pub use broadcast::{broadcast, BroadcastError};
-pub use descriptor::descriptor;
+pub use descriptor::{descriptor, DescriptorError, DescriptorTemplate, Heir, ScriptType, Timelock};
 pub use details::{psbt_details, BalanceError};
-pub use identity::{identity, IdentityError};
-pub use import::{import, ImportError};
+pub use encrypt::{decrypt, encrypt, to_identity, EncryptError};
+pub use entropy::{system_random, EntropySource};
+pub use export::{export, ExportError, ExportFormat};
+pub use identity::{
+    hardened_identity, identity, identity_combine, identity_shares, IdentityError, IdentityPath,
+    Split,
+};
+pub use import::{import, Birthday, ImportError};
 pub use locktime::{locktime, LocktimeError};
 pub use qr::qr;
 pub use refresh::{refresh, RefreshError};
-pub use seed::{seed, Seed, SeedError};
+pub use seed::{final_words, seed, seed_combine, seed_shares, Secret, Seed, SeedError};
 pub use sign::{sign, SignError};
+pub use verify_address::{verify_address, VerifyAddressError};
+pub use verify_backup::{verify_backup, Expected, VerifyBackupError};
+pub use wallet_policy::{wallet_policy, WalletPolicy, WalletPolicyError};
+pub use worksheet::{codex32_worksheet, WorksheetError};
 
 use crate::Descriptor;
 
@@ -37,9 +62,9 @@ pub enum Commands {
     /// ```
     /// # use dinasty::test_util::*;
     /// let stdin = "43242535241352135351234134123421351351342134123412351341324134134213512512353513123423423433222413233";
-    /// let stdout = sh(&stdin, "dinasty seed");
+    /// let stdout = sh(&stdin, "dinasty seed --force");
     /// assert_eq!(stdout, "flock audit wash crater album salon goose december envelope scissors lock suit render endorse prevent radio expose defy squirrel into grace broken culture burden");
-    /// let stdout = sh(&stdin, "dinasty seed --codex32-id leet");
+    /// let stdout = sh(&stdin, "dinasty seed --force --codex32-id leet");
     /// assert_eq!(stdout, "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd");
     /// ```
     ///
@@ -51,15 +76,122 @@ pub enum Commands {
     /// * press enter when you finish
     /// * press Ctrl+D to terminate the stdin
     ///
+    /// Before emitting the seed a statistical report of the sequence is logged: the frequency of
+    /// each face, the longest run of equal values and the chi-square test. If the sequence doesn't
+    /// look random, like the one in the example above where the 6 is missing, the command fails
+    /// unless `--force` is used.
+    ///
+    /// Other sources of randomness could be used with `--entropy`, for example coin flips or a
+    /// shuffled deck of cards, each requiring a different minimum length to reach 256 bits.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "AS 7D QH 2C 9S TD 4H KC 3D 8H JS 5C 6D AH 2S 9D QC 7H 3S TC 4D KH 8S JC 5D 6H AC 2D 9H QS 7C 3H TS 4C KD 8D JH 5S 6C AD 2H 9C QD 7S 3C TH 4S KS 8C JD 5H 6S 7S 9H QD 2S KC TD";
+    /// let stdout = sh(&stdin, "dinasty seed --entropy cards");
+    /// assert_eq!(stdout.to_string().split(' ').count(), 24);
+    /// ```
+    ///
+    /// With `--mix` the entropy of the sequence is mixed with the random number generator of the
+    /// operating system: the seed is unpredictable if any of the two is, but it cannot be recreated
+    /// from the sequence anymore, so the seed itself must be backed up.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "43242535241352135351234134123421351351342134123412351341324134134213512512353513123423423433222413233";
+    /// let first = sh(&stdin, "dinasty seed --force --mix").to_string();
+    /// let second = sh(&stdin, "dinasty seed --force --mix").to_string();
+    /// assert_eq!(first.split(' ').count(), 24);
+    /// assert_ne!(first, second);
+    /// ```
+    ///
+    /// With `--final-word` the stdin contains all the words of a bip39 mnemonic but the last, and
+    /// every word completing a mnemonic with a valid checksum is printed, one per line.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "flock audit wash crater album salon goose december envelope scissors lock suit render endorse prevent radio expose defy squirrel into grace broken culture";
+    /// let stdout = sh(&stdin, "dinasty seed --final-word");
+    /// assert_eq!(stdout, "burden\ndilemma\nfunny\nimpulse\nlove\nraise\nsurprise\ntwelve");
+    /// ```
+    ///
+    /// With `--threshold` and `--shares` the secret is split in Codex32 shares, one per line, any
+    /// `threshold` of them recover the secret with the `seed-combine` command.
+    ///
     #[clap(verbatim_doc_comment)]
     Seed {
         /// If specified the output will be encoded in Codex32 (bip93)
         #[arg(long)]
         codex32_id: Option<String>,
+
+        /// The kind of random sequence given in stdin
+        #[arg(long, value_enum, default_value_t = EntropySource::Dice)]
+        entropy: EntropySource,
+
+        /// Emit the seed even if the sequence doesn't pass the statistical checks
+        #[arg(long)]
+        force: bool,
+
+        /// Mix the entropy of the sequence with the system random number generator
+        #[arg(long)]
+        mix: bool,
+
+        /// The stdin contains a mnemonic without the last word, print the valid last words
+        #[arg(long, conflicts_with_all = ["codex32_id", "threshold", "entropy", "force", "mix"])]
+        final_word: bool,
+
+        /// Number of Codex32 shares needed to recover the secret, from 2 to 9
+        #[arg(long, requires_all = ["codex32_id", "shares"])]
+        threshold: Option<usize>,
+
+        /// Number of Codex32 shares created, at most 31
+        #[arg(long, requires = "threshold")]
+        shares: Option<usize>,
     },
 
+    /// Recover the Codex32 secret from Codex32 shares given in stdin, one per line
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "43242535241352135351234134123421351351342134123412351341324134134213512512353513123423423433222413233";
+    /// let shares = sh(&stdin, "dinasty seed --force --codex32-id leet --threshold 2 --shares 3").to_string();
+    /// let shares: Vec<_> = shares.split("\n").collect();
+    /// assert_eq!(shares.len(), 3);
+    /// let stdin = format!("{}\n{}", shares[2], shares[0]);
+    /// let stdout = sh(&stdin, "dinasty seed-combine");
+    /// let stdout = sh(&stdout.to_string(), "dinasty -n regtest descriptor --public --account 0");
+    /// assert_eq!(stdout, "tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)");
+    /// ```
+    #[clap(verbatim_doc_comment)]
+    SeedCombine,
+
+    /// Print the worksheets to verify by hand the Codex32 strings given in stdin, one per line
+    ///
+    /// For every string the table of the checksum computation is printed: every row shifts the
+    /// residue appending the next character and adds the leading character multiplied by the
+    /// generator, as done with the volvelles. The final residue of a valid string is `secretshare32`,
+    /// the table is printed for invalid strings too, to find the mistake by hand.
+    ///
+    /// If more shares are given, the recovery table is printed too: every share is multiplied by its
+    /// recovery symbol and the sum of the products is the secret. If the shares cannot be combined,
+    /// for example because they are less than the threshold, the reason is printed instead.
+    ///
+    /// Print the output and keep it with the backup, so that it could be verified even without
+    /// this tool.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+    /// let stdout = sh(&stdin, "dinasty codex32-worksheet").to_string();
+    /// assert!(stdout.ends_with("final residue: secretshare32 (valid)"));
+    /// ```
+    #[clap(verbatim_doc_comment)]
+    Codex32Worksheet,
+
     /// Given a seed, an account, if public or private prints the bip86 descriptor
     ///
+    /// The seed could be a bip39 mnemonic, a Codex32 secret or enough Codex32 shares to recover the
+    /// secret, one per line. A bip39 passphrase could be given, see `fingerprint` command.
+    ///
     /// ```
     /// # use dinasty::test_util::*;
     /// let stdin = "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
@@ -72,6 +204,33 @@ pub enum Commands {
     /// let stdout = sh(&stdin, "dinasty -n regtest descriptor --public --account 1");
     /// assert_eq!(stdout, "tr([01e0b4da/86h/1h/1h]tpubDCDuxkQNjPhqtq5ARHKc6t5QPg8CUyqJ6uzVkLqDBQtJ47Fac1JwrMUN9Zr6c3dAD5bGxL3DihfZUisSuszupSLoanydKxT8giNcVJSo2vq/<0;1>/*)");
     /// ```
+    ///
+    /// With `--heir` the descriptor has a script path spendable by the heir key only after the
+    /// timelock, relative with `--older` blocks or absolute with `--after` height or timestamp,
+    /// while the owner could always spend with the internal key.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+    /// let heir = "[01e0b4da/86h/1h/1h]tpubDCDuxkQNjPhqtq5ARHKc6t5QPg8CUyqJ6uzVkLqDBQtJ47Fac1JwrMUN9Zr6c3dAD5bGxL3DihfZUisSuszupSLoanydKxT8giNcVJSo2vq";
+    /// let stdout = sh(&stdin, &format!("dinasty -n regtest descriptor --public --account 0 --heir {heir} --older 52560"));
+    /// assert_eq!(stdout, format!("tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*,and_v(v:pk({heir}/<0;1>/*),older(52560)))"));
+    /// let stdout = sh(&stdin, &format!("dinasty -n regtest descriptor --public --account 0 --heir {heir} --after 1000000"));
+    /// assert!(stdout.to_string().ends_with("after(1000000)))"));
+    /// ```
+    ///
+    /// Segwit v0 descriptors are created with `--script-type wpkh`, using bip84 paths, or with
+    /// `--script-type wsh`, a sorted multisig with the given `--cosigner` keys using bip48 paths.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+    /// let stdout = sh(&stdin, "dinasty -n regtest descriptor --public --account 0 --script-type wpkh");
+    /// assert_eq!(stdout, "wpkh([01e0b4da/84h/1h/0h]tpubDDh27ZBN4jMWEm2Bk7WXPTPSQmB6BwcdASzk5PSMRDCtqWRQGStHZ8EGYogXKCCcMQo31kxZ1LFQGbHZNJ5ejciPR5GzPx3qWri4C8yNNKG/<0;1>/*)");
+    /// let cosigner = "[8335dcdb/48h/1h/0h/2h]tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1";
+    /// let stdout = sh(&stdin, &format!("dinasty -n regtest descriptor --public --account 0 --script-type wsh --threshold 2 --cosigner {cosigner}"));
+    /// assert_eq!(stdout, format!("wsh(sortedmulti(2,[01e0b4da/48h/1h/0h/2h]tpubDFEFeyFLnuBLAfrgBf2LhxZAdkQNJksdWEEtdALqNA84fseVeQKULuCnxpgFsUAobzSW2HmuYrLPefYxSr1rKNwmXC84BgMdRZWLMMDK8AP/<0;1>/*,{cosigner}/<0;1>/*))"));
+    /// ```
     #[clap(verbatim_doc_comment)]
     Descriptor {
         /// If the flag is provided the descriptor will contain extended public keys instead of extended private keys
@@ -80,10 +239,120 @@ pub enum Commands {
 
         #[arg(long)]
         account: u16,
+
+        #[clap(flatten)]
+        options: DescriptorOptions,
+
+        #[clap(flatten)]
+        passphrase: PassphraseOptions,
+    },
+
+    /// Print the extended public key derived from the seed at the given path, with its key origin
+    ///
+    /// The output, like `[fingerprint/path]xpub`, could be given to another dinasty as `--heir` or
+    /// `--cosigner` key, without exposing the whole descriptor. With `--format slip132` the version
+    /// is inferred from the path, like `zpub` for bip84. Base58 is case sensitive, with
+    /// `--uppercase` the key and its origin are encoded as `UR:CRYPTO-HDKEY/...` (BCR-2020-007),
+    /// which fits the more compact QR alphanumeric mode and is scanned by other wallets, like
+    /// `dinasty account-xpub 86h/0h/0h --uppercase | dinasty qr`.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+    /// let stdout = sh(&stdin, "dinasty -n regtest account-xpub m/86h/1h/1h");
+    /// assert_eq!(stdout, "[01e0b4da/86h/1h/1h]tpubDCDuxkQNjPhqtq5ARHKc6t5QPg8CUyqJ6uzVkLqDBQtJ47Fac1JwrMUN9Zr6c3dAD5bGxL3DihfZUisSuszupSLoanydKxT8giNcVJSo2vq");
+    /// let stdout = sh(&stdin, "dinasty -n regtest account-xpub 84h/1h/0h --format slip132");
+    /// assert_eq!(stdout, "[01e0b4da/84h/1h/0h]vpub5Zeryz1r46K6GNT6dCxBmMs4jhnYKK8ZnbN1A3bSKHhEz28Egui5AZFAGMgZnswXkE2WuEFZYuiFoqZpY3QPFhttco8qYbkdPSzMf34frzL");
+    /// let stdout = sh(&stdin, "dinasty -n regtest account-xpub m/86h/1h/1h --uppercase");
+    /// assert!(stdout.to_string().starts_with("UR:CRYPTO-HDKEY/"));
+    /// ```
+    #[clap(verbatim_doc_comment)]
+    AccountXpub {
+        /// The derivation path, like `m/48h/0h/0h/2h`
+        path: String,
+
+        #[arg(long, value_enum, default_value_t)]
+        format: XpubFormat,
+
+        /// Print the key origin and the extended public key as uppercase `UR:CRYPTO-HDKEY`, for QR
+        /// codes
+        #[arg(long, conflicts_with = "format")]
+        uppercase: bool,
+
+        #[clap(flatten)]
+        passphrase: PassphraseOptions,
+    },
+
+    /// Print the fingerprint of the master key derived from the seed
+    ///
+    /// A bip39 passphrase (25th word) changes the fingerprint, so that it could be used to verify the
+    /// passphrase has been typed correctly. The passphrase could be given as the last line of the
+    /// stdin with `--passphrase` or from a file, like `--passphrase-file <(pass dinasty/passphrase)`
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+    /// let stdout = sh(&stdin, "dinasty fingerprint");
+    /// assert_eq!(stdout, "01e0b4da");
+    /// let stdin = "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd\nmy passphrase";
+    /// let stdout = sh(&stdin, "dinasty fingerprint --passphrase");
+    /// assert_eq!(stdout, "d2f525ca");
+    /// let stdout = sh(&stdin, "dinasty -n regtest descriptor --public --account 0 --passphrase");
+    /// assert!(stdout.to_string().starts_with("tr([d2f525ca/86h/1h/0h]tpub"));
+    /// let stdin = "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd\n\n";
+    /// let stdout = sh(&stdin, "dinasty fingerprint --passphrase");
+    /// assert_eq!(stdout, "01e0b4da", "empty passphrase line");
+    /// let stdin = "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd\n";
+    /// let err = sh_err(&stdin, "dinasty fingerprint --passphrase");
+    /// assert_eq!(err, "The secret is expected in stdin before the passphrase line");
+    /// ```
+    #[clap(verbatim_doc_comment)]
+    Fingerprint {
+        #[clap(flatten)]
+        passphrase: PassphraseOptions,
+    },
+
+    /// Verify a backup of the seed, re-typed from paper, matches the expected value
+    ///
+    /// The expected value is the fingerprint as printed by `dinasty fingerprint` or the public
+    /// descriptor as printed by `dinasty descriptor --public`, compared parsed so that `h` or `'`
+    /// hardened steps and the cosigners order don't matter. Secrets are never printed, when the
+    /// checksum of the backup is invalid the position of the wrong word or character is reported.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+    /// let stdout = sh(&stdin, "dinasty verify-backup 01e0b4da");
+    /// assert_eq!(stdout, "ok, the backup matches 01e0b4da");
+    /// let stdin = "ms10leatst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+    /// let stderr = sh_err(&stdin, "dinasty verify-backup 01e0b4da");
+    /// assert!(stderr.contains("character 7 differs"));
+    /// let stdin = "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+    /// let desc = "tr([01e0b4da/86'/1'/0']tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)";
+    /// let stdout = sh(&stdin, &format!("dinasty -n regtest verify-backup {desc}"));
+    /// assert_eq!(stdout, format!("ok, the backup matches {desc}"), "' hardened notation");
+    /// ```
+    #[clap(verbatim_doc_comment)]
+    VerifyBackup {
+        /// The expected fingerprint or public descriptor
+        expected: Expected,
+
+        #[clap(flatten)]
+        passphrase: PassphraseOptions,
     },
 
     /// Create an age recipient or identity from a seed
     ///
+    /// Many independent identities could be derived from the same seed with `--index` or `--label`,
+    /// for example to encrypt every heir package to a different key.
+    ///
+    /// With `--kdf-passphrase-file` the derivation is hardened with scrypt and the passphrase, so that
+    /// a leaked seed alone doesn't expose the encrypted packages.
+    ///
+    /// With `--split K-of-N` the private identity is split in N Codex32 shares with human readable
+    /// part `agesk`, so that trustees need K of them to rebuild the identity with `--combine`, giving
+    /// the shares in stdin instead of the seed.
+    ///
     /// ```
     /// # use dinasty::test_util::*;
     /// let stdin = "flock audit wash crater album salon goose december envelope scissors lock suit render endorse prevent radio expose defy squirrel into grace broken culture burden";
@@ -91,12 +360,120 @@ pub enum Commands {
     /// assert_eq!(stdout, "age16unvc0en3dcageh7vqtdj2cvmgzp57uz5zp7pz4rllagcdr2v58scwpffw");
     /// let stdout = sh(&stdin, "dinasty -n regtest identity --private");
     /// assert_eq!(stdout, "AGE-SECRET-KEY-1FPURSK70MHN40TSPY7Q546WPJVLS0FS8V3H6XJU3CDX5D3UDM9ZQT4L4MN");
+    /// let stdout = sh(&stdin, "dinasty -n regtest identity --index 1");
+    /// assert_eq!(stdout, "age1mf92wgug2vatfsh3dcfsf8w2ryewce7ks269m6efmu382uh44egs36msqu");
+    /// let stdout = sh(&stdin, "dinasty -n regtest identity --label alice");
+    /// assert_eq!(stdout, "age1a04m6388gr7rxepwp6cjl4q6rccve9esc7n8vlhurnsz7vduvdysgl9ls0");
+    ///
+    /// let kdf = tempfile::NamedTempFile::new().unwrap();
+    /// std::fs::write(kdf.path(), "kdf secret\n").unwrap();
+    /// let stdout = sh(&stdin, &format!("dinasty identity --kdf-passphrase-file {}", kdf.path().display()));
+    /// assert_eq!(stdout, "age1x97kzqfqaxdqkay3jdcy2jsgklh4mlag9cd8eq7658jmg7yjfgfs7xdp09");
+    ///
+    /// let shares = sh(&stdin, "dinasty identity --private --split 2-of-3").to_string();
+    /// let shares: Vec<_> = shares.split("\n").collect();
+    /// assert_eq!(shares.len(), 3);
+    /// assert!(shares[0].starts_with("agesk12"));
+    /// let stdout = sh(&format!("{}\n{}", shares[0], shares[2]), "dinasty identity --combine --private");
+    /// assert_eq!(stdout, "AGE-SECRET-KEY-1FPURSK70MHN40TSPY7Q546WPJVLS0FS8V3H6XJU3CDX5D3UDM9ZQT4L4MN");
     /// ```
     #[clap(verbatim_doc_comment)]
     Identity {
         /// If the flag is provided the decryption secret key will be printed
         #[arg(long)]
         private: bool,
+
+        /// Split the decryption secret key in K-of-N Codex32 shares, like 2-of-3
+        #[arg(long, requires = "private")]
+        split: Option<Split>,
+
+        /// Rebuild the identity from the Codex32 shares given in stdin, one per line
+        #[arg(long, conflicts_with_all = ["split", "passphrase", "passphrase_file", "index", "label", "kdf_passphrase_file"])]
+        combine: bool,
+
+        #[clap(flatten)]
+        passphrase: PassphraseOptions,
+
+        #[clap(flatten)]
+        identity: IdentityOptions,
+    },
+
+    /// Encrypt the stdin to the given age recipients
+    ///
+    /// The output is binary unless `--armor` is used.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let seed = "flock audit wash crater album salon goose december envelope scissors lock suit render endorse prevent radio expose defy squirrel into grace broken culture burden";
+    /// let recipient = sh(&seed, "dinasty identity").to_string();
+    /// let ciphertext = sh("my secret", &format!("dinasty encrypt --armor --recipient {recipient}")).to_string();
+    /// assert!(ciphertext.starts_with("-----BEGIN AGE ENCRYPTED FILE-----"));
+    ///
+    /// let file = tempfile::NamedTempFile::new().unwrap();
+    /// std::fs::write(file.path(), ciphertext).unwrap();
+    /// let stdout = sh(&seed, &format!("dinasty decrypt {}", file.path().display()));
+    /// assert_eq!(stdout, "my secret");
+    /// ```
+    #[clap(verbatim_doc_comment)]
+    Encrypt {
+        /// The age recipient, like `age1...`, could be repeated to encrypt to many recipients
+        #[arg(long, required = true)]
+        recipient: Vec<age::x25519::Recipient>,
+
+        /// Output the ciphertext ascii armored
+        #[arg(long)]
+        armor: bool,
+    },
+
+    /// Decrypt the given file, armored or binary, with the identity derived from the seed in stdin
+    ///
+    /// The stdin could also contain an age identity like `AGE-SECRET-KEY-1...` or enough Codex32
+    /// shares of it, as created by `identity --private --split`.
+    #[clap(verbatim_doc_comment)]
+    Decrypt {
+        /// The age encrypted file
+        file: PathBuf,
+
+        #[clap(flatten)]
+        passphrase: PassphraseOptions,
+
+        #[clap(flatten)]
+        identity: IdentityOptions,
+    },
+
+    /// Derive a child secret from the seed as specified in bip85
+    ///
+    /// Child secrets are deterministic and independent from each other, so that a new wallet could
+    /// be created and later recovered just from the parent seed and the index used.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "flock audit wash crater album salon goose december envelope scissors lock suit render endorse prevent radio expose defy squirrel into grace broken culture burden";
+    /// let stdout = sh(&stdin, "dinasty bip85 bip39 --words 12");
+    /// assert_eq!(stdout, "dose wide pill observe submit traffic august reason bonus shrug palm inner");
+    /// let stdout = sh(&stdin, "dinasty bip85 hex --length 16 --index 1");
+    /// assert_eq!(stdout, "cc6b65b7078f03cc2fe1170535e59c41");
+    /// ```
+    #[clap(verbatim_doc_comment)]
+    Bip85 {
+        /// The kind of child secret to derive
+        #[arg(value_enum)]
+        application: Bip85Application,
+
+        /// Number of words of the child mnemonic, used with the bip39 application
+        #[arg(long, default_value_t = 24)]
+        words: u32,
+
+        /// Number of bytes of the child entropy, used with the hex application
+        #[arg(long, default_value_t = 32)]
+        length: u32,
+
+        /// The index of the child secret
+        #[arg(long, default_value_t = 0)]
+        index: u32,
+
+        #[clap(flatten)]
+        passphrase: PassphraseOptions,
     },
 
     /// Connects to bitcoin core, importing the descriptor given from stdin.
@@ -105,6 +482,15 @@ pub enum Commands {
     ///
     /// If the `--with-private-keys` flag is used, the given private descriptor is used as wallet passphrase
     ///
+    /// By default only transactions after the import are found, to find existing funds, like when
+    /// re-creating a wallet after a machine failure, give the wallet `--birthday` as a unix
+    /// timestamp, a block height or a date like 2023-12-31, or `--rescan` from the genesis block.
+    /// The rescan progress is logged.
+    ///
+    /// With `--existing` the command could be repeated: the wallet is loaded or created if needed
+    /// and only the descriptors it doesn't already contain are imported. A signer wallet is
+    /// unlocked with the private descriptor, as passphrase, for the import and locked afterwards.
+    ///
     /// ```
     /// # use dinasty::test_util::*;
     /// # let TestNode { node, core_connect_params, .. } = setup_node();
@@ -119,6 +505,12 @@ pub enum Commands {
     /// let stdin = "tr([01e0b4da/86h/1h/1h]tpubDCDuxkQNjPhqtq5ARHKc6t5QPg8CUyqJ6uzVkLqDBQtJ47Fac1JwrMUN9Zr6c3dAD5bGxL3DihfZUisSuszupSLoanydKxT8giNcVJSo2vq/<0;1>/*)";
     /// let stdout = sh(&stdin, &format!("dinasty {core_connect_params} import --wallet-name heir_watch_only")).to_string();
     /// assert!(stdout.contains("ok"));
+    ///
+    /// let stdout = sh(&stdin, &format!("dinasty {core_connect_params} import --wallet-name heir_restored --birthday 1")).to_string();
+    /// assert!(stdout.contains("ok"));
+    ///
+    /// let stdout = sh(&stdin, &format!("dinasty {core_connect_params} import --wallet-name heir_restored --existing")).to_string();
+    /// assert_eq!(stdout, "ok, 0 imported, 2 already in the wallet");
     /// ```
     ///
     /// This wallet setup example is used in other doc tests via [`crate::test_util::setup_node_and_wallets()`]
@@ -128,8 +520,8 @@ pub enum Commands {
         #[arg(short, long, required = true)]
         wallet_name: String,
 
-        #[arg(long)]
-        with_private_keys: bool,
+        #[clap(flatten)]
+        options: ImportOptions,
     },
 
     /// For every old UTXO creates a locktimed transaction to another wallet
@@ -148,6 +540,13 @@ pub enum Commands {
     /// let signed_psbt = stdout.to_psbts().unwrap()[0].clone();
     /// let tx = signed_psbt.extract_tx();
     /// assert_eq!(tx.lock_time, bitcoin::absolute::LockTime::from_height(301).unwrap());
+    ///
+    /// // the PSBTs could be encrypted to many recipients, any of them could decrypt
+    /// let heir = age::x25519::Identity::generate();
+    /// let lawyer = age::x25519::Identity::generate();
+    /// let stdout = sh("", &format!("dinasty {core_connect_params} locktime --locktime-future 200 --from-wallet-name watch_only --to-wallet-name heir_watch_only --recipient {} --recipient {}", heir.to_public(), lawyer.to_public()));
+    /// let psbts = dinasty::psbts_age::decrypt(stdout.as_ref(), &lawyer).unwrap();
+    /// assert_eq!(psbts.len(), 1);
     /// ```
     ///
     #[clap(verbatim_doc_comment)]
@@ -163,6 +562,9 @@ pub enum Commands {
         /// Default value equals to about 4 years
         #[arg(long, default_value_t = 210_240)]
         locktime_future: i64,
+
+        #[clap(flatten)]
+        encrypt: EncryptOptions,
     },
 
     /// Refresh owned UTXO with the goal of invalidating previously generated locktimed transactions
@@ -223,6 +625,9 @@ pub enum Commands {
         /// file containing one or more psbt in binary format
         #[arg(long, required = true)]
         psbt_file: PathBuf,
+
+        #[clap(flatten)]
+        encrypt: EncryptOptions,
     },
 
     /// Broadcast the PSBTs given from stdin.
@@ -275,8 +680,118 @@ pub enum Commands {
     #[clap(verbatim_doc_comment)]
     Details {
         /// The public descriptor (multipath) to calculate the net balance against.
+        #[arg(long, value_parser = import::parse_descriptor)]
+        descriptor: Vec<Descriptor>,
+    },
+
+    /// Print the addresses of a public multipath descriptor, without connecting to a node
+    ///
+    /// Every line contains an address and the key origins with the full derivation path of the
+    /// keys, receive addresses by default or change addresses with `--change`.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let desc = "tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)";
+    /// let stdout = sh("", &format!("dinasty -n regtest addresses --descriptor {desc} --count 2"));
+    /// assert_eq!(stdout, "bcrt1pccadr74cd29xf5y0eax2dwnfvjeqwa65c9h09f7cw6c2h6c7rjyswwdgx4 [01e0b4da/86h/1h/0h/0/0]\nbcrt1ps4e34gzelyrt0uvujgz7p5tdjzt7qz8kgnnt4zvle3u8twvhhcfqa8e6sr [01e0b4da/86h/1h/0h/0/1]");
+    /// let stdout = sh("", &format!("dinasty -n regtest addresses --descriptor {desc} --change --start 5 --count 1"));
+    /// assert_eq!(stdout, "bcrt1pfzheprtkfvjvgm4m3yagcgce2rue8mwa3pdmmhz5jvc039eqrwtsmu0z0y [01e0b4da/86h/1h/0h/1/5]");
+    /// ```
+    #[clap(verbatim_doc_comment)]
+    Addresses {
+        /// The public descriptor, multipath like `/<0;1>/*` to derive change addresses
+        #[arg(long, value_parser = import::parse_descriptor)]
+        descriptor: Descriptor,
+
+        /// Derive change addresses instead of receive addresses
         #[arg(long)]
+        change: bool,
+
+        /// Index of the first address
+        #[arg(long, default_value_t = 0)]
+        start: u32,
+
+        /// How many addresses are printed
+        #[arg(long, default_value_t = 10)]
+        count: u32,
+    },
+
+    /// Export the public multipath descriptor in a format importable by other wallets
+    ///
+    /// Formats are a bip129 BSMS descriptor record, a Specter wallet JSON (imported also by
+    /// Sparrow), a Coldcard generic JSON for single key descriptors and the `importdescriptors`
+    /// payload for bitcoin core, like `bitcoin-cli importdescriptors "$(dinasty export ...)"`.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let desc = "tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)";
+    /// let stdout = sh("", &format!("dinasty -n regtest export bsms --descriptor {desc}"));
+    /// assert_eq!(stdout, "BSMS 1.0\ntr([01e0b4da/86'/1'/0']tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/**)#70qaztex\n/0/*,/1/*\nbcrt1pccadr74cd29xf5y0eax2dwnfvjeqwa65c9h09f7cw6c2h6c7rjyswwdgx4");
+    /// let stdout = sh("", &format!("dinasty -n regtest export specter --descriptor {desc} --label heir"));
+    /// assert!(stdout.to_string().contains(r#""label": "heir""#));
+    /// ```
+    #[clap(verbatim_doc_comment)]
+    Export {
+        #[arg(value_enum)]
+        format: ExportFormat,
+
+        /// The public descriptor (multipath) of the wallet
+        #[arg(long, value_parser = import::parse_descriptor)]
+        descriptor: Descriptor,
+
+        /// The name of the wallet, where supported by the format
+        #[arg(long, default_value = "dinasty")]
+        label: String,
+    },
+
+    /// Convert the public multipath descriptor on stdin to a bip388 wallet policy, or the other
+    /// way around with `--to-descriptor`
+    ///
+    /// The wallet policy is the descriptor template with key placeholders like `@0/**` on the
+    /// first line, followed by the keys information, one per line. It's shorter to show in a QR
+    /// and it's the format registered by signing devices.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let stdin = "tr([01e0b4da/86'/1'/0']tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*,and_v(v:pk([8335dcdb/48'/1'/0'/2']tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1/<0;1>/*),older(1000)))";
+    /// let policy = sh(&stdin, "dinasty wallet-policy");
+    /// assert_eq!(policy, "tr(@0/**,and_v(v:pk(@1/**),older(1000)))\n[01e0b4da/86'/1'/0']tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X\n[8335dcdb/48'/1'/0'/2']tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1");
+    /// let descriptor = sh(policy.to_string(), "dinasty wallet-policy --to-descriptor");
+    /// assert!(descriptor.to_string().starts_with(stdin));
+    /// ```
+    #[clap(verbatim_doc_comment)]
+    WalletPolicy {
+        /// Read the wallet policy from stdin and print the descriptor
+        #[arg(long)]
+        to_descriptor: bool,
+    },
+
+    /// Verify the address is derived by one of the given public multipath descriptors
+    ///
+    /// Both the receive and the change branches are searched in the first `--gap` indexes, the
+    /// command fails if the address is not found. Could be used on the offline machine to check
+    /// the outputs of a PSBT, like the heir addresses of the locktime transactions.
+    ///
+    /// ```
+    /// # use dinasty::test_util::*;
+    /// let desc = "tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)";
+    /// let address = "bcrt1pfzheprtkfvjvgm4m3yagcgce2rue8mwa3pdmmhz5jvc039eqrwtsmu0z0y";
+    /// let stdout = sh("", &format!("dinasty -n regtest verify-address {address} --descriptor {desc}"));
+    /// assert_eq!(stdout, format!("ok, the address {address} is the change address at index 5 of descriptor 0"));
+    /// let err = sh_err("", &format!("dinasty -n regtest verify-address {address} --descriptor {desc} --gap 5"));
+    /// assert!(err.contains("is NOT derived"));
+    /// ```
+    #[clap(verbatim_doc_comment)]
+    VerifyAddress {
+        address: Address<NetworkUnchecked>,
+
+        /// The public descriptor (multipath) the address should belong to, could be repeated
+        #[arg(long, required = true, value_parser = import::parse_descriptor)]
         descriptor: Vec<Descriptor>,
+
+        /// How many addresses are derived for every branch of every descriptor
+        #[arg(long, default_value_t = 1_000)]
+        gap: u32,
     },
 
     /// Convert the text given on stdin into 1 or more QR codes
@@ -303,6 +818,173 @@ pub enum Commands {
     GenerateCompletion { shell: Shell },
 }
 
+#[derive(Debug, Args)]
+pub struct PassphraseOptions {
+    /// The last line of the stdin is the bip39 passphrase (25th word)
+    #[arg(long, conflicts_with = "passphrase_file")]
+    pub passphrase: bool,
+
+    /// File containing the bip39 passphrase (25th word), a trailing new line is ignored
+    #[arg(long)]
+    pub passphrase_file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct EncryptOptions {
+    /// Encrypt the resulting PSBTs to this age recipient, could be repeated to encrypt to many
+    /// recipients, like the heir, a backup heir and a lawyer, each of them could decrypt alone
+    #[arg(long)]
+    pub recipient: Vec<age::x25519::Recipient>,
+
+    /// Output the encrypted PSBTs ascii armored
+    #[arg(long, requires = "recipient")]
+    pub armor: bool,
+}
+
+impl EncryptOptions {
+    /// Serialize the PSBTs, encrypted if there are recipients
+    pub fn serialize(
+        &self,
+        psbts: &[bitcoin::psbt::PartiallySignedTransaction],
+    ) -> Result<Vec<u8>, crate::psbts_age::PsbtsAgeError> {
+        if self.recipient.is_empty() {
+            Ok(crate::psbts_serde::serialize(psbts))
+        } else {
+            crate::psbts_age::encrypt(psbts, &self.recipient, self.armor)
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct IdentityOptions {
+    /// Derive the age identity at the given index instead of the master one
+    #[arg(long, conflicts_with = "label")]
+    pub index: Option<u32>,
+
+    /// Derive the age identity with the given label instead of the master one, like the heir name
+    #[arg(long)]
+    pub label: Option<String>,
+
+    /// File containing a passphrase used to harden the identity derivation with scrypt, so that
+    /// the seed alone is not enough to decrypt. A trailing new line is ignored
+    #[arg(long)]
+    pub kdf_passphrase_file: Option<PathBuf>,
+}
+
+impl IdentityOptions {
+    /// Read the passphrase used to harden the identity derivation, if given
+    pub fn kdf_passphrase(&self) -> std::io::Result<Option<String>> {
+        self.kdf_passphrase_file
+            .as_ref()
+            .map(|path| {
+                let content = std::fs::read_to_string(path)?;
+                Ok(content.strip_suffix('\n').unwrap_or(&content).to_string())
+            })
+            .transpose()
+    }
+
+    pub fn path(&self) -> IdentityPath {
+        match (self.index, self.label.as_ref()) {
+            (Some(index), _) => IdentityPath::Index(index),
+            (None, Some(label)) => IdentityPath::Label(label.clone()),
+            (None, None) => IdentityPath::Master,
+        }
+    }
+}
+
+#[derive(Debug, Default, Args)]
+pub struct ImportOptions {
+    #[arg(long)]
+    pub with_private_keys: bool,
+
+    /// Rescan the chain for existing funds since this unix timestamp, block height or date like
+    /// 2023-12-31
+    #[arg(long)]
+    pub birthday: Option<Birthday>,
+
+    /// Rescan the whole chain for existing funds, like a birthday at the genesis block
+    #[arg(long, conflicts_with = "birthday")]
+    pub rescan: bool,
+
+    /// Import in the wallet even if it exists, skipping the descriptors it already contains
+    #[arg(long)]
+    pub existing: bool,
+}
+
+impl ImportOptions {
+    /// The birthday from which the chain is rescanned, if any
+    pub fn birthday(&self) -> Option<Birthday> {
+        self.birthday.or(self.rescan.then_some(Birthday::GENESIS))
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct DescriptorOptions {
+    #[arg(long, value_enum, default_value_t)]
+    pub script_type: ScriptType,
+
+    /// Extended public key of a cosigner of the `wsh` multisig, optionally with origin like
+    /// `[fingerprint/path]xpub`, could be repeated
+    #[arg(long, required_if_eq("script_type", "wsh"))]
+    pub cosigner: Vec<String>,
+
+    /// How many keys are required to spend from the `wsh` multisig
+    #[arg(long, required_if_eq("script_type", "wsh"))]
+    pub threshold: Option<usize>,
+
+    /// Extended public key of the heir, optionally with origin like `[fingerprint/path]xpub`,
+    /// added in a taproot script path spendable only after `--older` or `--after`
+    #[arg(long, requires = "timelock")]
+    pub heir: Option<String>,
+
+    /// Blocks since the output confirmation before the heir could spend
+    #[arg(long, group = "timelock", requires = "heir")]
+    pub older: Option<u16>,
+
+    /// Block height, or unix timestamp if greater than 500 millions, after which the heir could
+    /// spend
+    #[arg(long, group = "timelock", requires = "heir")]
+    pub after: Option<u32>,
+}
+
+impl DescriptorOptions {
+    pub fn template(&self) -> Result<DescriptorTemplate, DescriptorError> {
+        let heir = self.heir();
+        if heir.is_some() && self.script_type != ScriptType::Tr {
+            return Err(DescriptorError::HeirRequiresTaproot);
+        }
+        let multisig_options = !self.cosigner.is_empty() || self.threshold.is_some();
+        if multisig_options && self.script_type != ScriptType::Wsh {
+            return Err(DescriptorError::CosignersRequireMultisig);
+        }
+        Ok(match self.script_type {
+            ScriptType::Tr => DescriptorTemplate::Taproot { heir },
+            ScriptType::Wpkh => DescriptorTemplate::Wpkh,
+            ScriptType::Wsh => DescriptorTemplate::SortedMulti {
+                threshold: self.threshold.unwrap_or(1),
+                cosigners: self.cosigner.clone(),
+            },
+        })
+    }
+
+    fn heir(&self) -> Option<Heir> {
+        let timelock = match (self.older, self.after) {
+            (Some(blocks), _) => Timelock::Older(blocks),
+            (None, Some(lock)) => Timelock::After(lock),
+            (None, None) => return None,
+        };
+        self.heir.as_ref().map(|key| Heir {
+            key: key.clone(),
+            timelock,
+        })
+    }
+}
+
+/// The descriptor without the `#checksum` suffix, if any
+pub(crate) fn without_checksum(desc: &str) -> &str {
+    desc.split_once('#').map(|(d, _)| d).unwrap_or(desc)
+}
+
 #[derive(Debug, Args)]
 pub struct CoreConnectOptional {
     /// The bitcoin core node url, if not provided defaults to the network default
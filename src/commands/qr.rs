@@ -43,7 +43,7 @@ fn print_qr(
     qr: &QrCode,
     border: u8,
     result: &mut String,
-    empty_lines: &String,
+    empty_lines: &str,
     len: usize,
     label: &str,
 ) {
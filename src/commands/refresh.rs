@@ -99,8 +99,17 @@ mod test {
         let xprv_desc = "tr([8335dcdb/48'/1'/0'/2']tprv8ifUoGVh57yDBkyW2sS6kMNv7ewZVLmSLp1RSgZw4H5AhMP6AtxJB1P842vZcvdu9giYEfWDa6NX5nCGaaUVK5boJt1AeA8fFKv2u87Ua3g/<0;1>/*)";
         let xpub_desc = "tr([8335dcdb/48'/1'/0'/2']tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1/<0;1>/*)";
 
-        commands::import(&core_connect, xpub_desc, "wo", false).unwrap();
-        commands::import(&core_connect, xprv_desc, "signer", true).unwrap();
+        commands::import(&core_connect, xpub_desc, "wo", &Default::default()).unwrap();
+        commands::import(
+            &core_connect,
+            xprv_desc,
+            "signer",
+            &commands::ImportOptions {
+                with_private_keys: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
 
         let wo_client = core_connect.client_with_wallet("wo").unwrap();
         let signer_client = core_connect.client_with_wallet("signer").unwrap();
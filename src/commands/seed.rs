@@ -1,23 +1,45 @@
 use std::str::FromStr;
 
-use bip39::Mnemonic;
+use bip39::{Language, Mnemonic};
 use bitcoin::{
     bip32::{ExtendedPrivKey, Fingerprint},
-    hashes::{sha256, Hash},
+    hashes::{hmac, sha256, Hash, HashEngine},
     secp256k1::Secp256k1,
     Network,
 };
 use codex32::{Codex32String, Fe};
 
+use super::{
+    correction::{correct, Correction},
+    entropy::mix,
+    EntropySource,
+};
 use crate::error::Codex32ErrorWrapper;
 
 #[derive(thiserror::Error, Debug)]
 pub enum SeedError {
-    #[error("The dice launches sequence is too short to achieve 256 bits of entropy: {0}<={1}")]
+    #[error("The entropy sequence is too short to achieve 256 bits of entropy: {0}<={1}")]
     TooShort(usize, usize),
 
-    #[error("The sequence contain a character ({0}) that is not in the dictionary: 1,2,3,4,5,6")]
-    NonDictionaryChar(char),
+    #[error("The sequence contain a character ({0}) that is not in the dictionary: {1}")]
+    NonDictionaryChar(char, &'static str),
+
+    #[error("The sequence contain an invalid element: {0}")]
+    InvalidSymbol(String),
+
+    #[error("The card {0} is repeated in the same deck")]
+    RepeatedCard(String),
+
+    #[error("The word '{0}' is not in the bip39 english word list")]
+    UnknownWord(String),
+
+    #[error(
+        "Given {0} words, must be one less than a valid mnemonic length: 11, 14, 17, 20 or 23"
+    )]
+    InvalidWordCount(usize),
+
+    #[error("The sequence doesn't look random: {0}, check it or use --force")]
+    NonRandom(String),
 
     #[error(transparent)]
     Bip39(#[from] bip39::Error),
@@ -25,59 +47,307 @@ pub enum SeedError {
     #[error(transparent)]
     Codex32(#[from] Codex32ErrorWrapper), // wrapping is needed because codex32::Error doesn't support Display
 
+    #[error("The Codex32 string has an invalid checksum, correcting the highlighted characters it would be:\n{0}")]
+    Codex32Correction(Correction),
+
     #[error("The string '{0}' cannot be interpreted neither as Mnemonic nor as Code32")]
     NeitherMnemonicNorCodex32(String),
+
+    #[error("The Codex32 string has human readable part '{0}' instead of 'ms', it's not a seed")]
+    UnexpectedHrp(String),
+
+    #[error("Invalid threshold {threshold} and number of shares {shares}: must be 2<=threshold<=shares<=31 and threshold<=9")]
+    InvalidThresholdShares { threshold: usize, shares: usize },
+
+    #[error("No Codex32 shares given")]
+    NoShares,
+
+    #[error("Codex32 shares have different identifiers: {0} and {1}")]
+    MismatchedShareId(String, String),
+
+    #[error("Codex32 shares have different thresholds: {0} and {1}")]
+    MismatchedShareThreshold(char, char),
+
+    #[error("Codex32 share index {0} is repeated")]
+    RepeatedShareIndex(char),
+
+    #[error(
+        "Not enough Codex32 shares to recover the secret: threshold is {threshold}, given {given}"
+    )]
+    NotEnoughShares { threshold: char, given: usize },
 }
 
-pub fn seed(dices: &str, codex32_id: Option<String>) -> Result<Seed, SeedError> {
-    let needed_launches = (256f64 / 6f64.log2()) as usize;
+/// Share indexes in the order they are assigned, `s` is excluded because reserved for the secret
+const SHARE_INDEXES: &str = "acdefghjklmnpqrtuvwxyz023456789";
 
-    if dices.len() < needed_launches {
-        return Err(SeedError::TooShort(dices.len(), needed_launches));
-    }
+/// Create a seed from the entropy of the given sequence, mixed with the `random` bytes if given,
+/// encoded in Codex32 if `codex32_id` is given or else as bip39 mnemonic.
+pub fn seed(
+    sequence: &str,
+    source: EntropySource,
+    codex32_id: Option<String>,
+    random: Option<&[u8; 32]>,
+) -> Result<Seed, SeedError> {
+    let result = secret(sequence, source, random)?;
+    Ok(match codex32_id {
+        Some(id) => Codex32String::from_seed("ms", 0, &id, Fe::S, &result)
+            .map_err(Codex32ErrorWrapper)?
+            .into(),
+        None => Mnemonic::from_entropy(&result)?.into(),
+    })
+}
 
-    for el in dices.chars() {
-        if !"123456".contains(el) {
-            return Err(SeedError::NonDictionaryChar(el));
+/// Given all the words of a mnemonic but the last, returns every word that completes a mnemonic
+/// with a valid checksum.
+///
+/// Useful when the words are chosen offline, for example drawing word cards, since the checksum
+/// in the last word cannot be computed by hand.
+pub fn final_words(words: &str) -> Result<Vec<&'static str>, SeedError> {
+    let language = Language::English;
+    let words: Vec<_> = words.split_whitespace().collect();
+    if !matches!(words.len(), 11 | 14 | 17 | 20 | 23) {
+        return Err(SeedError::InvalidWordCount(words.len()));
+    }
+    for word in words.iter() {
+        if language.find_word(word).is_none() {
+            return Err(SeedError::UnknownWord(word.to_string()));
         }
     }
+    let words = words.join(" ");
 
-    let result = sha256::Hash::hash(dices.as_bytes());
-    let result = result.as_byte_array();
-    Ok(match codex32_id {
-        Some(id) => Seed::Codex32(
-            Codex32String::from_seed("ms", 0, &id, Fe::S, result).map_err(Codex32ErrorWrapper)?,
-        ),
-        None => Seed::Mnemonic(Mnemonic::from_entropy(result)?),
+    Ok(language
+        .word_list()
+        .iter()
+        .filter(|last| Mnemonic::parse_in_normalized(language, &format!("{words} {last}")).is_ok())
+        .copied()
+        .collect())
+}
+
+/// Split the entropy of the given sequence, mixed with the `random` bytes if given, in `shares`
+/// Codex32 (bip93) shares, any `threshold` of them are enough to recover the secret.
+///
+/// The first `threshold - 1` shares are derived from the secret with HMAC-SHA256, so that the same
+/// sequence always gives the same shares, the others are interpolated as described in bip93.
+pub fn seed_shares(
+    sequence: &str,
+    source: EntropySource,
+    codex32_id: &str,
+    threshold: usize,
+    shares: usize,
+    random: Option<&[u8; 32]>,
+) -> Result<Vec<Codex32String>, SeedError> {
+    let secret = secret(sequence, source, random)?;
+    codex32_shares("ms", &secret, codex32_id, threshold, shares)
+}
+
+fn secret(
+    sequence: &str,
+    source: EntropySource,
+    random: Option<&[u8; 32]>,
+) -> Result<[u8; 32], SeedError> {
+    let entropy = source.entropy(sequence)?;
+    Ok(random.map_or(entropy, |random| mix(entropy, random)))
+}
+
+/// Split the secret in `shares` Codex32 shares with the given human readable part, see
+/// [`seed_shares`]
+pub(crate) fn codex32_shares(
+    hrp: &str,
+    secret: &[u8],
+    codex32_id: &str,
+    threshold: usize,
+    shares: usize,
+) -> Result<Vec<Codex32String>, SeedError> {
+    if !(2..=9).contains(&threshold) || shares < threshold || shares > SHARE_INDEXES.len() {
+        return Err(SeedError::InvalidThresholdShares { threshold, shares });
+    }
+    let secret_share = Codex32String::from_seed(hrp, threshold, codex32_id, Fe::S, secret)
+        .map_err(Codex32ErrorWrapper)?;
+
+    let mut indexes = SHARE_INDEXES
+        .chars()
+        .map(|c| Fe::from_char(c).expect("valid chars"));
+
+    let mut result = vec![];
+    for index in indexes.by_ref().take(threshold - 1) {
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(secret);
+        engine.input(b"dinasty codex32 share");
+        engine.input(codex32_id.as_bytes());
+        engine.input(&[index.to_u8()]);
+        let data = hmac::Hmac::<sha256::Hash>::from_engine(engine);
+        let data = &data.as_byte_array()[..secret.len()];
+        let share = Codex32String::from_seed(hrp, threshold, codex32_id, index, data)
+            .map_err(Codex32ErrorWrapper)?;
+        result.push(share);
+    }
+
+    let mut base = result.clone();
+    base.push(secret_share);
+    for index in indexes.take(shares - result.len()) {
+        let share = Codex32String::interpolate_at(&base, index).map_err(Codex32ErrorWrapper)?;
+        result.push(share);
+    }
+
+    Ok(result)
+}
+
+/// Parse a Codex32 string, if the checksum is invalid but up to 4 substitutions could fix it the
+/// error contains the suggested correction
+pub(crate) fn parse_codex32(s: &str) -> Result<Codex32String, SeedError> {
+    Codex32String::from_string(s.to_string()).map_err(|e| match correct(s) {
+        Some(correction) => SeedError::Codex32Correction(correction),
+        None => Codex32ErrorWrapper(e).into(),
     })
 }
 
-pub enum Seed {
+/// Recover the secret share `s` from at least `threshold` Codex32 shares
+pub fn seed_combine(shares: &[String]) -> Result<Codex32String, SeedError> {
+    let shares = shares
+        .iter()
+        .map(|s| parse_codex32(s.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let first = ShareHeader::new(shares.first().ok_or(SeedError::NoShares)?);
+    let mut indexes = vec![];
+    for share in shares.iter() {
+        let header = ShareHeader::new(share);
+        if header.id != first.id {
+            return Err(SeedError::MismatchedShareId(first.id, header.id));
+        }
+        if header.threshold != first.threshold {
+            return Err(SeedError::MismatchedShareThreshold(
+                first.threshold,
+                header.threshold,
+            ));
+        }
+        if indexes.contains(&header.index) {
+            return Err(SeedError::RepeatedShareIndex(header.index));
+        }
+        indexes.push(header.index);
+    }
+    if indexes.contains(&'s') {
+        // the secret is already among the shares, no need to interpolate
+        let secret = shares
+            .into_iter()
+            .find(|s| ShareHeader::new(s).index == 's');
+        return Ok(secret.expect("checked"));
+    }
+    if first.threshold_n() > shares.len() {
+        return Err(SeedError::NotEnoughShares {
+            threshold: first.threshold,
+            given: shares.len(),
+        });
+    }
+
+    Ok(Codex32String::interpolate_at(&shares, Fe::S).map_err(Codex32ErrorWrapper)?)
+}
+
+/// The header of a Codex32 string, parsed here because [`codex32::Parts`] doesn't expose it
+struct ShareHeader {
+    hrp: String,
+    threshold: char,
+    id: String,
+    index: char,
+}
+
+impl ShareHeader {
+    fn new(share: &Codex32String) -> Self {
+        let s = share.to_string().to_ascii_lowercase();
+        let (hrp, data) = s
+            .rsplit_once('1')
+            .expect("valid codex32 contains separator");
+        let mut chars = data.chars();
+        let threshold = chars.next().expect("valid codex32");
+        let id: String = chars.by_ref().take(4).collect();
+        let index = chars.next().expect("valid codex32");
+        Self {
+            hrp: hrp.to_string(),
+            threshold,
+            id,
+            index,
+        }
+    }
+
+    fn check_seed_hrp(&self) -> Result<(), SeedError> {
+        if self.hrp != "ms" {
+            return Err(SeedError::UnexpectedHrp(self.hrp.clone()));
+        }
+        Ok(())
+    }
+
+    fn threshold_n(&self) -> usize {
+        self.threshold.to_digit(10).expect("valid codex32") as usize
+    }
+}
+
+/// The secret from which keys are derived, with the optional bip39 passphrase (25th word)
+#[derive(Clone)]
+pub struct Seed {
+    secret: Secret,
+    passphrase: String,
+}
+
+#[derive(Clone)]
+pub enum Secret {
     Mnemonic(Mnemonic),
     Codex32(Codex32String),
 }
 
+impl From<Mnemonic> for Seed {
+    fn from(mnemonic: Mnemonic) -> Self {
+        Seed::new(Secret::Mnemonic(mnemonic))
+    }
+}
+
+impl From<Codex32String> for Seed {
+    fn from(codex32: Codex32String) -> Self {
+        Seed::new(Secret::Codex32(codex32))
+    }
+}
+
+/// Only the secret is printed, the passphrase is never shown
 impl std::fmt::Display for Seed {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Seed::Mnemonic(e) => write!(f, "{}", e),
-            Seed::Codex32(e) => write!(f, "{}", e),
+        match &self.secret {
+            Secret::Mnemonic(e) => write!(f, "{}", e),
+            Secret::Codex32(e) => write!(f, "{}", e),
         }
     }
 }
 
 impl Seed {
+    pub fn new(secret: Secret) -> Self {
+        Self {
+            secret,
+            passphrase: String::new(),
+        }
+    }
+
+    /// Set the bip39 passphrase, any string, even empty, is a valid passphrase
+    pub fn with_passphrase(mut self, passphrase: &str) -> Self {
+        self.passphrase = passphrase.to_string();
+        self
+    }
+
+    pub fn passphrase(&self) -> &str {
+        &self.passphrase
+    }
+
+    pub fn secret(&self) -> &Secret {
+        &self.secret
+    }
+
     pub fn mnemonic(&self) -> Mnemonic {
-        match self {
-            Seed::Mnemonic(e) => e.clone(),
-            Seed::Codex32(e) => {
+        match &self.secret {
+            Secret::Mnemonic(e) => e.clone(),
+            Secret::Codex32(e) => {
                 Mnemonic::from_entropy(&e.parts().data()).expect("guaranteed 32 bytes")
             }
         }
     }
     pub fn xprv(&self, network: Network) -> Result<ExtendedPrivKey, bitcoin::bip32::Error> {
         let mnemonic = self.mnemonic();
-        ExtendedPrivKey::new_master(network, &mnemonic.to_seed(""))
+        ExtendedPrivKey::new_master(network, &mnemonic.to_seed(&self.passphrase))
     }
 
     pub fn fingerprint(&self) -> Result<Fingerprint, bitcoin::bip32::Error> {
@@ -89,11 +359,35 @@ impl Seed {
 impl FromStr for Seed {
     type Err = SeedError;
 
+    /// Parse a Mnemonic, a Codex32 secret or multiple Codex32 shares, one per line
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<_> = s
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(ToString::to_string)
+            .collect();
+        if lines.len() > 1 {
+            let codex32 = seed_combine(&lines)?;
+            ShareHeader::new(&codex32).check_seed_hrp()?;
+            return Ok(codex32.into());
+        }
+        let s = s.trim();
+
         if let Ok(mnemonic) = s.parse::<Mnemonic>() {
-            Ok(Seed::Mnemonic(mnemonic))
+            Ok(mnemonic.into())
         } else if let Ok(codex32) = Codex32String::from_string(s.to_string()) {
-            Ok(Seed::Codex32(codex32))
+            let header = ShareHeader::new(&codex32);
+            header.check_seed_hrp()?;
+            if header.index != 's' {
+                return Err(SeedError::NotEnoughShares {
+                    threshold: header.threshold,
+                    given: 1,
+                });
+            }
+            Ok(codex32.into())
+        } else if let Some(correction) = correct(s) {
+            Err(SeedError::Codex32Correction(correction))
         } else {
             Err(SeedError::NeitherMnemonicNorCodex32(s.to_string()))
         }
@@ -104,6 +398,8 @@ impl FromStr for Seed {
 mod test {
     use std::str::FromStr;
 
+    use super::{Seed, SeedError};
+    use crate::commands::{system_random, EntropySource};
     use bip39::Mnemonic;
     use bitcoin::{bip32::ExtendedPrivKey, secp256k1::Secp256k1, Network};
     use codex32::Codex32String;
@@ -145,9 +441,10 @@ mod test {
     #[test]
     fn match_39_93_all_networks() {
         let dices = DICES;
-        let seed_mnemonic = super::seed(dices, None).unwrap();
+        let seed_mnemonic = super::seed(dices, EntropySource::Dice, None, None).unwrap();
         assert_eq!(seed_mnemonic.to_string(), MNEMONIC);
-        let seed_codex32 = super::seed(dices, Some("leet".to_string())).unwrap();
+        let seed_codex32 =
+            super::seed(dices, EntropySource::Dice, Some("leet".to_string()), None).unwrap();
         assert_eq!(seed_codex32.to_string(), CODEX_32);
         for network in [
             Network::Bitcoin,
@@ -160,4 +457,146 @@ mod test {
             assert_eq!(seed_codex32.fingerprint(), seed_mnemonic.fingerprint());
         }
     }
+
+    #[test]
+    fn mixed_entropy() {
+        let leet = || Some("leet".to_string());
+        let random = [1u8; 32];
+        let mixed = super::seed(DICES, EntropySource::Dice, leet(), Some(&random)).unwrap();
+        assert_ne!(mixed.to_string(), CODEX_32);
+        let again = super::seed(DICES, EntropySource::Dice, leet(), Some(&random)).unwrap();
+        assert_eq!(mixed.to_string(), again.to_string());
+        let other = super::seed(DICES, EntropySource::Dice, leet(), Some(&[2u8; 32])).unwrap();
+        assert_ne!(mixed.to_string(), other.to_string());
+
+        let shares =
+            super::seed_shares(DICES, EntropySource::Dice, "leet", 2, 3, Some(&random)).unwrap();
+        let shares: Vec<_> = shares[..2].iter().map(ToString::to_string).collect();
+        let secret = super::seed_combine(&shares).unwrap();
+        let expected = Codex32String::from_string(mixed.to_string()).unwrap();
+        assert_eq!(secret.parts().data(), expected.parts().data());
+
+        assert_ne!(system_random(), system_random());
+    }
+
+    #[test]
+    fn shares_combine() {
+        let shares = super::seed_shares(DICES, EntropySource::Dice, "leet", 3, 5, None).unwrap();
+        assert_eq!(shares.len(), 5);
+        let shares: Vec<_> = shares.iter().map(ToString::to_string).collect();
+        assert!(shares.iter().all(|s| s.starts_with("ms13leet")));
+
+        let expected = Codex32String::from_string(CODEX_32.to_string()).unwrap();
+        for subset in [[0, 1, 2], [0, 2, 4], [4, 3, 1], [1, 2, 3]] {
+            let selected: Vec<_> = subset.iter().map(|i| shares[*i].clone()).collect();
+            let secret = super::seed_combine(&selected).unwrap();
+            assert_eq!(secret.parts().data(), expected.parts().data());
+        }
+
+        super::seed_combine(&shares[..2]).unwrap_err();
+        let repeated = vec![shares[0].clone(), shares[1].clone(), shares[0].clone()];
+        super::seed_combine(&repeated).unwrap_err();
+        super::seed_shares(DICES, EntropySource::Dice, "leet", 1, 5, None).unwrap_err();
+        super::seed_shares(DICES, EntropySource::Dice, "leet", 3, 2, None).unwrap_err();
+        super::seed_shares(DICES, EntropySource::Dice, "leet", 3, 32, None).unwrap_err();
+    }
+
+    #[test]
+    fn codex32_correction() {
+        let mistyped = CODEX_32.replacen("st9q", "sx9p", 1);
+        match Seed::from_str(&mistyped) {
+            Err(SeedError::Codex32Correction(correction)) => {
+                assert_eq!(correction.corrected.to_string(), CODEX_32);
+                assert_eq!(correction.positions, vec![9, 11]);
+            }
+            _ => panic!("expected a correction"),
+        }
+
+        let shares = super::seed_shares(DICES, EntropySource::Dice, "leet", 2, 3, None).unwrap();
+        let mistyped = shares[1].to_string().replacen("leet", "lett", 1);
+        let stdin = format!("{}\n{}", shares[0], mistyped);
+        assert!(matches!(
+            Seed::from_str(&stdin),
+            Err(SeedError::Codex32Correction(..))
+        ));
+    }
+
+    #[test]
+    fn seed_from_shares() {
+        let expected = Seed::from_str(MNEMONIC).unwrap();
+        let shares = super::seed_shares(DICES, EntropySource::Dice, "leet", 2, 3, None).unwrap();
+
+        let stdin = format!("{}\n{}\n", shares[1], shares[2]);
+        let seed = Seed::from_str(&stdin).unwrap();
+        assert_eq!(seed.fingerprint(), expected.fingerprint());
+        assert_eq!(seed.xprv(Network::Testnet), expected.xprv(Network::Testnet));
+
+        assert!(matches!(
+            Seed::from_str(&shares[0].to_string()),
+            Err(SeedError::NotEnoughShares { .. })
+        ));
+
+        let other = super::seed_shares(DICES, EntropySource::Dice, "dyna", 2, 3, None).unwrap();
+        let stdin = format!("{}\n{}", shares[0], other[1]);
+        assert!(matches!(
+            Seed::from_str(&stdin),
+            Err(SeedError::MismatchedShareId(..))
+        ));
+
+        let other = super::seed_shares(DICES, EntropySource::Dice, "leet", 3, 3, None).unwrap();
+        let stdin = format!("{}\n{}", shares[0], other[1]);
+        assert!(matches!(
+            Seed::from_str(&stdin),
+            Err(SeedError::MismatchedShareThreshold('2', '3'))
+        ));
+    }
+
+    #[test]
+    fn final_words() {
+        let words: Vec<_> = MNEMONIC.split(' ').collect();
+        let candidates = super::final_words(&words[..23].join(" ")).unwrap();
+        assert_eq!(candidates.len(), 8);
+        assert!(candidates.contains(&"burden"));
+
+        let candidates = super::final_words(&words[..11].join(" ")).unwrap();
+        assert_eq!(candidates.len(), 128);
+
+        assert!(matches!(
+            super::final_words(&words[..12].join(" ")),
+            Err(SeedError::InvalidWordCount(12))
+        ));
+        assert!(matches!(
+            super::final_words(&format!("{} notaword", words[..10].join(" "))),
+            Err(SeedError::UnknownWord(_))
+        ));
+    }
+
+    #[test]
+    fn passphrase() {
+        let seed = Seed::from_str(MNEMONIC).unwrap();
+        let fingerprint = seed.fingerprint().unwrap();
+        assert_eq!(fingerprint.to_string(), "01e0b4da");
+
+        let seed = seed.with_passphrase("");
+        assert_eq!(seed.fingerprint().unwrap(), fingerprint);
+
+        let seed = seed.with_passphrase("TREZOR");
+        assert_ne!(seed.fingerprint().unwrap(), fingerprint);
+        assert_eq!(seed.to_string(), MNEMONIC, "passphrase is never printed");
+
+        let seed_codex32 = Seed::from_str(CODEX_32).unwrap().with_passphrase("TREZOR");
+        assert_eq!(seed_codex32.fingerprint(), seed.fingerprint());
+    }
+
+    #[test]
+    fn bip39_passphrase_vector() {
+        // from https://github.com/trezor/python-mnemonic/blob/master/vectors.json
+        let mnemonic =
+            "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let seed = Seed::from_str(mnemonic).unwrap().with_passphrase("TREZOR");
+        assert_eq!(
+            seed.xprv(Network::Bitcoin).unwrap().to_string(),
+            "xprv9s21ZrQH143K2gA81bYFHqU68xz1cX2APaSq5tt6MFSLeXnCKV1RVUJt9FWNTbrrryem4ZckN8k4Ls1H6nwdvDTvnV7zEXs2HgPezuVccsq"
+        );
+    }
 }
@@ -1,10 +1,13 @@
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 
 use bitcoin::psbt::{PartiallySignedTransaction, PsbtParseError};
 use bitcoind::bitcoincore_rpc::{self, RpcApi};
 
 use crate::{client_ext::ClientExt, core_connect::CoreConnect};
 
+/// Signing doesn't take long, the wallet is locked again anyway as soon as the PSBTs are signed
+const UNLOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(thiserror::Error, Debug)]
 pub enum SignError {
     #[error(transparent)]
@@ -24,7 +27,7 @@ pub fn sign(
     psbts: &[PartiallySignedTransaction],
 ) -> Result<Vec<PartiallySignedTransaction>, SignError> {
     let client = core_connect.client_with_wallet(wallet_name)?;
-    client.wallet_passphrase(descriptor);
+    client.wallet_passphrase(descriptor, UNLOCK_TIMEOUT)?;
 
     let mut results = vec![];
     for psbt in psbts {
@@ -36,7 +39,7 @@ pub fn sign(
 
         results.push(signed_psbt);
     }
-    client.wallet_lock();
+    client.wallet_lock()?;
     Ok(results)
 }
 
@@ -64,8 +67,17 @@ mod test {
         let xprv_desc = "tr([8335dcdb/48'/1'/0'/2']tprv8ifUoGVh57yDBkyW2sS6kMNv7ewZVLmSLp1RSgZw4H5AhMP6AtxJB1P842vZcvdu9giYEfWDa6NX5nCGaaUVK5boJt1AeA8fFKv2u87Ua3g/<0;1>/*)";
         let xpub_desc = "tr([8335dcdb/48'/1'/0'/2']tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1/<0;1>/*)";
 
-        commands::import(&core_connect, xpub_desc, "wo", false).unwrap();
-        commands::import(&core_connect, xprv_desc, "signer", true).unwrap();
+        commands::import(&core_connect, xpub_desc, "wo", &Default::default()).unwrap();
+        commands::import(
+            &core_connect,
+            xprv_desc,
+            "signer",
+            &commands::ImportOptions {
+                with_private_keys: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
 
         let wo_client = core_connect.client_with_wallet("wo").unwrap();
         let signer_client = core_connect.client_with_wallet("signer").unwrap();
@@ -0,0 +1,96 @@
+use bitcoin::{address::NetworkUnchecked, Address, Network};
+
+use super::details::{BalanceError, MyScripts};
+use crate::Descriptor;
+
+#[derive(thiserror::Error, Debug)]
+pub enum VerifyAddressError {
+    #[error(transparent)]
+    Address(#[from] bitcoin::address::Error),
+
+    #[error(transparent)]
+    Balance(#[from] BalanceError),
+
+    #[error("The address {address} is NOT derived by the given descriptors in the first {gap} indexes of both branches")]
+    NotFound { address: String, gap: u32 },
+}
+
+/// Search the address in the first `gap` indexes of the receive and change branches of the given
+/// multipath descriptors, returning where it has been found.
+pub fn verify_address(
+    address: &Address<NetworkUnchecked>,
+    descriptors: &[Descriptor],
+    network: Network,
+    gap: u32,
+) -> Result<String, VerifyAddressError> {
+    let address = address.clone().require_network(network)?;
+    let my_scripts = MyScripts::new(descriptors, gap)?;
+
+    match my_scripts.get(&address.script_pubkey()) {
+        Some(origin) => {
+            let branch = if origin.branch == 0 {
+                "receive"
+            } else {
+                "change"
+            };
+            Ok(format!(
+                "ok, the address {address} is the {branch} address at index {} of descriptor {}",
+                origin.index, origin.descriptor
+            ))
+        }
+        None => Err(VerifyAddressError::NotFound {
+            address: address.to_string(),
+            gap,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::Network;
+
+    use super::{verify_address, VerifyAddressError};
+    use crate::Descriptor;
+
+    #[test]
+    fn test_verify_address() {
+        let desc: Descriptor = "tr(tpubD6NzVbkrYhZ4XUprtHTHAWupukJFpWBJBBU9pyp62LVMhxnpb1dqDouxv5m2MTTAuWzLvFQmtgWwzHCFTrVXi1HscGm1BZ2xuGDN5KL4zNF/<0;1>/*)".parse().unwrap();
+        let other: Descriptor = "tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)".parse().unwrap();
+        let verify = |address: &str, descriptors: &[Descriptor], gap| {
+            verify_address(
+                &address.parse().unwrap(),
+                descriptors,
+                Network::Regtest,
+                gap,
+            )
+        };
+
+        let change = "bcrt1p76yqr3phgr7ratf0tcjszltrztut28v9nd20krquf5y2qq342ylqfv0qfu";
+        assert_eq!(
+            verify(change, &[other.clone(), desc.clone()], 10).unwrap(),
+            format!("ok, the address {change} is the change address at index 0 of descriptor 1")
+        );
+
+        let receive = "bcrt1ps4e34gzelyrt0uvujgz7p5tdjzt7qz8kgnnt4zvle3u8twvhhcfqa8e6sr";
+        assert_eq!(
+            verify(receive, &[other.clone()], 10).unwrap(),
+            format!("ok, the address {receive} is the receive address at index 1 of descriptor 0")
+        );
+        assert!(matches!(
+            verify(receive, &[other.clone()], 1),
+            Err(VerifyAddressError::NotFound { gap: 1, .. })
+        ));
+
+        let foreign = "bcrt1qzuszgwlscs7awaj9rhlvm6kk4ajvxuf4qs9ue9";
+        assert!(matches!(
+            verify(foreign, &[other.clone(), desc], 100),
+            Err(VerifyAddressError::NotFound { .. })
+        ));
+
+        let mainnet = "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr";
+        assert!(matches!(
+            verify(mainnet, &[other], 10),
+            Err(VerifyAddressError::Address(_))
+        ));
+    }
+}
@@ -0,0 +1,516 @@
+use std::{str::FromStr, sync::Arc};
+
+use bip39::{Language, Mnemonic};
+use bitcoin::{
+    bip32::{ChildNumber, DerivationPath, Fingerprint},
+    Network,
+};
+use codex32::Codex32String;
+use miniscript::{
+    descriptor::{DescriptorMultiXKey, TapTree, Wildcard, WshInner},
+    DescriptorPublicKey, Miniscript, Tap, Terminal,
+};
+
+use super::{
+    account_xpub::origin, descriptor, descriptor::DescriptorError, without_checksum,
+    DescriptorTemplate, Heir, Seed, SeedError, Timelock,
+};
+use crate::Descriptor;
+
+const CODEX32_CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Errors never contain the secret or part of it, only positions
+#[derive(thiserror::Error, Debug)]
+pub enum VerifyBackupError {
+    #[error(transparent)]
+    Bip32(#[from] bitcoin::bip32::Error),
+
+    #[error(transparent)]
+    Descriptor(#[from] DescriptorError),
+
+    #[error(transparent)]
+    Seed(#[from] SeedError),
+
+    #[error("The expected value must be a fingerprint or a public descriptor with key origin")]
+    InvalidExpected,
+
+    #[error("The expected descriptor must be a tr, wpkh or wsh(sortedmulti) descriptor with the key path of its script type, as printed by the descriptor command")]
+    UnsupportedDescriptor,
+
+    #[error("Given {0} words, a mnemonic has 12, 15, 18, 21 or 24 words")]
+    InvalidWordCount(usize),
+
+    #[error("Word {0} is not in the bip39 english word list")]
+    UnknownWord(usize),
+
+    #[error("Character {0} is not valid in a Codex32 string")]
+    InvalidChar(usize),
+
+    #[error("The checksum is invalid, word {0} differs from the backup")]
+    WrongWord(usize),
+
+    #[error("The checksum is invalid, character {0} differs from the backup")]
+    WrongChar(usize),
+
+    #[error("The checksum is invalid and changing a single word or character doesn't match the expected value")]
+    InvalidChecksum,
+
+    #[error("The backup is valid but it doesn't match: expected {expected} found {found}")]
+    Mismatch { expected: String, found: String },
+}
+
+/// What the backup is compared against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expected {
+    Fingerprint(Fingerprint),
+
+    /// A public descriptor shaped like the ones printed by `descriptor --public`, the template
+    /// and the account are taken from the parsed descriptor
+    Descriptor {
+        descriptor: Descriptor,
+
+        /// The descriptor as given, without checksum, used in messages
+        value: String,
+    },
+}
+
+impl FromStr for Expected {
+    type Err = VerifyBackupError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(fingerprint) = Fingerprint::from_str(s) {
+            return Ok(Expected::Fingerprint(fingerprint));
+        }
+        let compact: String = s.split_whitespace().collect();
+        let descriptor =
+            Descriptor::from_str(&compact).map_err(|_| VerifyBackupError::InvalidExpected)?;
+        template(&descriptor, None).ok_or(VerifyBackupError::UnsupportedDescriptor)?;
+        Ok(Expected::Descriptor {
+            descriptor,
+            value: without_checksum(s).to_string(),
+        })
+    }
+}
+
+/// The template and the account of a descriptor shaped like the ones created by the `descriptor`
+/// command.
+///
+/// The owner, the key derived from the seed, is the sortedmulti key with the `owner` fingerprint
+/// or the first one, and the taproot internal key.
+fn template(
+    descriptor: &Descriptor,
+    owner: Option<Fingerprint>,
+) -> Option<(DescriptorTemplate, u16)> {
+    let (template, owner) = match descriptor {
+        Descriptor::Wpkh(wpkh) => (DescriptorTemplate::Wpkh, key_origin(wpkh.as_inner())?),
+        Descriptor::Wsh(wsh) => {
+            let WshInner::SortedMulti(multi) = wsh.as_inner() else {
+                return None;
+            };
+            let mut keys: Vec<_> = multi.pks.iter().map(key_origin).collect::<Option<_>>()?;
+            let index = keys
+                .iter()
+                .position(|(fingerprint, ..)| Some(*fingerprint) == owner)
+                .unwrap_or(0);
+            let owner = keys.remove(index);
+            let cosigners = keys.iter().map(|(f, p, xpub)| key(*f, p, xpub)).collect();
+            let template = DescriptorTemplate::SortedMulti {
+                threshold: multi.k,
+                cosigners,
+            };
+            (template, owner)
+        }
+        Descriptor::Tr(tr) => {
+            let heir = match tr.taptree() {
+                None => None,
+                Some(TapTree::Leaf(script)) => Some(heir(script)?),
+                Some(_) => return None,
+            };
+            let template = DescriptorTemplate::Taproot { heir };
+            (template, key_origin(tr.internal_key())?)
+        }
+        _ => return None,
+    };
+    let account = account(&owner.1, &template)?;
+    Some((template, account))
+}
+
+/// The heir of the `and_v(v:pk(heir),older(N))` or `and_v(v:pk(heir),after(N))` script
+fn heir(script: &Arc<Miniscript<DescriptorPublicKey, Tap>>) -> Option<Heir> {
+    let Terminal::AndV(verify, timelock) = &script.node else {
+        return None;
+    };
+    let Terminal::Verify(check) = &verify.node else {
+        return None;
+    };
+    let Terminal::Check(pk) = &check.node else {
+        return None;
+    };
+    let Terminal::PkK(heir) = &pk.node else {
+        return None;
+    };
+    let timelock = match &timelock.node {
+        Terminal::Older(sequence) => {
+            Timelock::Older(sequence.to_consensus_u32().try_into().ok()?)
+        }
+        Terminal::After(lock) => Timelock::After(lock.to_consensus_u32()),
+        _ => return None,
+    };
+    let (fingerprint, path, xpub) = key_origin(heir)?;
+    Some(Heir {
+        key: key(fingerprint, &path, &xpub),
+        timelock,
+    })
+}
+
+/// The origin and the extended public key of a `[fingerprint/path]xpub/<0;1>/*` key
+fn key_origin(key: &DescriptorPublicKey) -> Option<(Fingerprint, DerivationPath, String)> {
+    let DescriptorPublicKey::MultiXPub(DescriptorMultiXKey {
+        origin: Some((fingerprint, path)),
+        xkey,
+        derivation_paths,
+        wildcard: Wildcard::Unhardened,
+    }) = key
+    else {
+        return None;
+    };
+    let steps: Vec<_> = derivation_paths.paths().iter().map(AsRef::as_ref).collect();
+    if steps[..] != [[ChildNumber::from(0)], [ChildNumber::from(1)]] {
+        return None;
+    }
+    Some((*fingerprint, path.clone(), xkey.to_string()))
+}
+
+/// The key as accepted by the `descriptor` command, like `[fingerprint/path]xpub`
+fn key(fingerprint: Fingerprint, path: &DerivationPath, xpub: &str) -> String {
+    format!("[{}]{xpub}", origin(fingerprint, path))
+}
+
+/// The account in the key origin path, if the path is the one of the script type of the
+/// template: bip86, bip84 or bip48
+fn account(path: &DerivationPath, template: &DescriptorTemplate) -> Option<u16> {
+    let steps: Vec<u32> = path
+        .into_iter()
+        .map(|step| match step {
+            ChildNumber::Hardened { index } => Some(*index),
+            ChildNumber::Normal { .. } => None,
+        })
+        .collect::<Option<_>>()?;
+    let (purpose, script_type) = match template {
+        DescriptorTemplate::Taproot { .. } => (86, None),
+        DescriptorTemplate::Wpkh => (84, None),
+        DescriptorTemplate::SortedMulti { .. } => (48, Some(2)),
+    };
+    match steps[..] {
+        [p, _, account] if p == purpose && script_type.is_none() => account.try_into().ok(),
+        [p, _, account, s] if p == purpose && script_type == Some(s) => account.try_into().ok(),
+        _ => None,
+    }
+}
+
+/// The descriptor with the sortedmulti keys sorted, so that the cosigners order doesn't matter
+fn normalized(descriptor: &Descriptor) -> Descriptor {
+    if let Descriptor::Wsh(wsh) = descriptor {
+        if let WshInner::SortedMulti(multi) = wsh.as_inner() {
+            let mut pks = multi.pks.clone();
+            pks.sort();
+            return Descriptor::new_wsh_sortedmulti(multi.k, pks)
+                .expect("keys of a valid sortedmulti");
+        }
+    }
+    descriptor.clone()
+}
+
+impl Expected {
+    /// Compute from the seed the value comparable with this expectation
+    fn found(&self, seed: &Seed, network: Network) -> Result<String, VerifyBackupError> {
+        Ok(match self {
+            Expected::Fingerprint(_) => seed.fingerprint()?.to_string(),
+            Expected::Descriptor {
+                descriptor: expected,
+                ..
+            } => {
+                let (template, account) = template(expected, Some(seed.fingerprint()?))
+                    .or_else(|| template(expected, None))
+                    .expect("checked when parsed");
+                descriptor(seed.clone(), network, account, true, &template)?
+            }
+        })
+    }
+
+    fn value(&self) -> String {
+        match self {
+            Expected::Fingerprint(fingerprint) => fingerprint.to_string(),
+            Expected::Descriptor { value, .. } => value.clone(),
+        }
+    }
+
+    /// Descriptors are compared parsed, so that `h` and `'` or a different cosigners order match
+    fn is_found(&self, found: &str) -> bool {
+        match self {
+            Expected::Fingerprint(fingerprint) => fingerprint.to_string() == found,
+            Expected::Descriptor { descriptor, .. } => Descriptor::from_str(found)
+                .map(|found| normalized(&found) == normalized(descriptor))
+                .unwrap_or(false),
+        }
+    }
+
+    fn matches(&self, seed: &Seed, network: Network) -> Result<bool, VerifyBackupError> {
+        Ok(self.is_found(&self.found(seed, network)?))
+    }
+}
+
+/// Verify the re-typed backup (a mnemonic or a Codex32 string) matches the expected value.
+///
+/// When the checksum is invalid, every single word or character substitution with a valid checksum
+/// is tried against the expected value to report the position that differs.
+pub fn verify_backup(
+    backup: &str,
+    passphrase: &str,
+    expected: &Expected,
+    network: Network,
+) -> Result<String, VerifyBackupError> {
+    let backup = backup.trim().to_lowercase();
+    let seed = if backup.starts_with("ms1") {
+        parse_codex32(&backup, passphrase, expected, network)?
+    } else {
+        parse_mnemonic(&backup, passphrase, expected, network)?
+    };
+
+    let found = expected.found(&seed, network)?;
+    if !expected.is_found(&found) {
+        return Err(VerifyBackupError::Mismatch {
+            expected: expected.value(),
+            found,
+        });
+    }
+    Ok(format!("ok, the backup matches {}", expected.value()))
+}
+
+fn parse_mnemonic(
+    backup: &str,
+    passphrase: &str,
+    expected: &Expected,
+    network: Network,
+) -> Result<Seed, VerifyBackupError> {
+    let language = Language::English;
+    let words: Vec<_> = backup.split_whitespace().collect();
+    if !matches!(words.len(), 12 | 15 | 18 | 21 | 24) {
+        return Err(VerifyBackupError::InvalidWordCount(words.len()));
+    }
+    if let Some(position) = words.iter().position(|w| language.find_word(w).is_none()) {
+        return Err(VerifyBackupError::UnknownWord(position + 1));
+    }
+
+    match Mnemonic::parse_in_normalized(language, &words.join(" ")) {
+        Ok(mnemonic) => Ok(Seed::from(mnemonic).with_passphrase(passphrase)),
+        Err(bip39::Error::InvalidChecksum) => {
+            let mut candidate = words.clone();
+            for position in 0..words.len() {
+                for word in language.word_list() {
+                    if *word == words[position] {
+                        continue;
+                    }
+                    candidate[position] = word;
+                    if let Ok(mnemonic) =
+                        Mnemonic::parse_in_normalized(language, &candidate.join(" "))
+                    {
+                        let seed = Seed::from(mnemonic).with_passphrase(passphrase);
+                        if expected.matches(&seed, network)? {
+                            return Err(VerifyBackupError::WrongWord(position + 1));
+                        }
+                    }
+                }
+                candidate[position] = words[position];
+            }
+            Err(VerifyBackupError::InvalidChecksum)
+        }
+        Err(e) => Err(SeedError::Bip39(e).into()),
+    }
+}
+
+fn parse_codex32(
+    backup: &str,
+    passphrase: &str,
+    expected: &Expected,
+    network: Network,
+) -> Result<Seed, VerifyBackupError> {
+    let data_start = "ms1".len();
+    if let Some(position) = backup[data_start..]
+        .chars()
+        .position(|c| !CODEX32_CHARSET.contains(c))
+    {
+        return Err(VerifyBackupError::InvalidChar(data_start + position + 1));
+    }
+
+    if Codex32String::from_string(backup.to_string()).is_ok() {
+        return Ok(Seed::from_str(backup)?.with_passphrase(passphrase));
+    }
+
+    let chars: Vec<char> = backup.chars().collect();
+    let mut candidate = chars.clone();
+    for position in data_start..chars.len() {
+        for c in CODEX32_CHARSET.chars() {
+            if c == chars[position] {
+                continue;
+            }
+            candidate[position] = c;
+            let candidate_str: String = candidate.iter().collect();
+            if Codex32String::from_string(candidate_str.clone()).is_ok() {
+                if let Ok(seed) = Seed::from_str(&candidate_str) {
+                    let seed = seed.with_passphrase(passphrase);
+                    if expected.matches(&seed, network)? {
+                        return Err(VerifyBackupError::WrongChar(position + 1));
+                    }
+                }
+            }
+        }
+        candidate[position] = chars[position];
+    }
+    Err(VerifyBackupError::InvalidChecksum)
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use bitcoin::Network;
+
+    use super::{verify_backup, Expected, VerifyBackupError};
+    use crate::commands::{descriptor, DescriptorTemplate, Heir, Seed, Timelock};
+
+    const MNEMONIC: &str = "flock audit wash crater album salon goose december envelope scissors lock suit render endorse prevent radio expose defy squirrel into grace broken culture burden";
+    const CODEX_32: &str =
+        "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+    const DESCRIPTOR: &str = "tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)";
+
+    #[test]
+    fn test_verify_backup() {
+        let fingerprint: Expected = "01e0b4da".parse().unwrap();
+        let descriptor: Expected = DESCRIPTOR.parse().unwrap();
+        let verify = |backup: &str, expected| verify_backup(backup, "", expected, Network::Regtest);
+
+        for expected in [&fingerprint, &descriptor] {
+            verify(MNEMONIC, expected).unwrap();
+            verify(CODEX_32, expected).unwrap();
+            verify(&CODEX_32.to_uppercase(), expected).unwrap();
+        }
+
+        let wrong_word = MNEMONIC.replace("salon", "salt");
+        assert!(matches!(
+            verify(&wrong_word, &fingerprint),
+            Err(VerifyBackupError::WrongWord(6))
+        ));
+        let unknown_word = MNEMONIC.replace("salon", "salonx");
+        assert!(matches!(
+            verify(&unknown_word, &fingerprint),
+            Err(VerifyBackupError::UnknownWord(6))
+        ));
+        assert!(matches!(
+            verify("flock audit", &fingerprint),
+            Err(VerifyBackupError::InvalidWordCount(2))
+        ));
+
+        let wrong_char = CODEX_32.replacen("leet", "leat", 1);
+        assert!(matches!(
+            verify(&wrong_char, &descriptor),
+            Err(VerifyBackupError::WrongChar(7))
+        ));
+        let invalid_char = CODEX_32.replacen("leet", "lbet", 1);
+        assert!(matches!(
+            verify(&invalid_char, &fingerprint),
+            Err(VerifyBackupError::InvalidChar(6))
+        ));
+
+        let other: Expected = "d2f525ca".parse().unwrap();
+        assert!(matches!(
+            verify(MNEMONIC, &other),
+            Err(VerifyBackupError::Mismatch { .. })
+        ));
+        assert!(matches!(
+            verify_backup(MNEMONIC, "my passphrase", &other, Network::Regtest),
+            Ok(_)
+        ));
+    }
+
+    #[test]
+    fn test_verify_backup_script_types() {
+        const COSIGNER: &str = "[8335dcdb/48h/1h/0h/2h]tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1";
+        let seed = Seed::from_str(MNEMONIC).unwrap();
+        let other = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let heir = |timelock| DescriptorTemplate::Taproot {
+            heir: Some(Heir {
+                key: COSIGNER.to_string(),
+                timelock,
+            }),
+        };
+        let templates = [
+            DescriptorTemplate::Taproot { heir: None },
+            heir(Timelock::Older(1000)),
+            heir(Timelock::After(900_000)),
+            DescriptorTemplate::Wpkh,
+            DescriptorTemplate::SortedMulti {
+                threshold: 2,
+                cosigners: vec![COSIGNER.to_string()],
+            },
+        ];
+
+        for template in templates {
+            let desc = descriptor(seed.clone(), Network::Regtest, 3, true, &template).unwrap();
+            let expected: Expected = desc.parse().unwrap();
+            let Expected::Descriptor { descriptor: parsed, .. } = &expected else {
+                panic!("expected a descriptor");
+            };
+            assert_eq!(super::template(parsed, None), Some((template, 3)));
+            verify_backup(MNEMONIC, "", &expected, Network::Regtest).unwrap();
+            assert!(matches!(
+                verify_backup(other, "", &expected, Network::Regtest),
+                Err(VerifyBackupError::Mismatch { .. })
+            ));
+        }
+
+        // written differently: `'` notation, whitespace and the cosigners in another order
+        let multi = DescriptorTemplate::SortedMulti {
+            threshold: 2,
+            cosigners: vec![COSIGNER.to_string()],
+        };
+        let desc = descriptor(seed.clone(), Network::Regtest, 3, true, &multi).unwrap();
+        let desc = super::without_checksum(&desc);
+        let (threshold, keys) = desc
+            .strip_prefix("wsh(sortedmulti(")
+            .and_then(|d| d.strip_suffix("))"))
+            .and_then(|d| d.split_once(','))
+            .unwrap();
+        let (owner, cosigner) = keys.split_once(',').unwrap();
+        let quoted = |desc: &str| desc.replace("h/", "'/").replace("h]", "']");
+        for equivalent in [
+            quoted(DESCRIPTOR),
+            DESCRIPTOR.replacen("86h", "86'", 1),
+            DESCRIPTOR.replace(',', ", ").replace("tr(", "tr( "),
+            format!("wsh(sortedmulti({threshold},{cosigner},{owner}))"),
+            format!("wsh(sortedmulti({threshold}, {}, {owner}))", quoted(cosigner)),
+        ] {
+            let expected: Expected = equivalent.parse().unwrap();
+            verify_backup(MNEMONIC, "", &expected, Network::Regtest).unwrap();
+            verify_backup(CODEX_32, "", &expected, Network::Regtest).unwrap();
+        }
+        let quoted: Expected = quoted(DESCRIPTOR).parse().unwrap();
+        let wrong_char = CODEX_32.replacen("leet", "leat", 1);
+        assert!(matches!(
+            verify_backup(&wrong_char, "", &quoted, Network::Regtest),
+            Err(VerifyBackupError::WrongChar(7))
+        ));
+
+        for unsupported in [
+            DESCRIPTOR.replace("86h", "84h"),
+            DESCRIPTOR.replace("tr(", "sh(wpkh(").replace(')', "))"),
+            format!("wsh(sortedmulti(1,{COSIGNER}/<0;1>/*))").replace("/2h]", "/1h]"),
+        ] {
+            assert!(matches!(
+                unsupported.parse::<Expected>(),
+                Err(VerifyBackupError::UnsupportedDescriptor)
+            ));
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Bip388 wallet policies: a descriptor template where keys are replaced by placeholders like
+//! `@0/**`, and the vector of the keys information like `[fingerprint/path]xpub`.
+
+use std::{fmt::Display, str::FromStr};
+
+use bitcoin::bip32::ChildNumber;
+use miniscript::{
+    descriptor::{DescriptorMultiXKey, Wildcard},
+    DescriptorPublicKey, ForEachKey,
+};
+
+use super::without_checksum;
+use crate::Descriptor;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WalletPolicyError {
+    #[error(transparent)]
+    Miniscript(#[from] miniscript::Error),
+
+    #[error("The key '{0}' must be an extended public key followed by `/<M;N>/*`")]
+    UnsupportedKey(String),
+
+    #[error("The wallet policy must contain the descriptor template and at least one key")]
+    MissingKeys,
+
+    #[error("The key placeholder @{0} has no key information")]
+    MissingKey(usize),
+}
+
+/// A descriptor template with key placeholders and the keys information
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletPolicy {
+    /// Like `tr(@0/**,and_v(v:pk(@1/**),older(1000)))`
+    pub template: String,
+
+    /// Like `[fingerprint/path]xpub`, the key at index `i` replaces the placeholder `@i`
+    pub keys: Vec<String>,
+}
+
+/// The template on the first line followed by a key per line
+impl Display for WalletPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.template)?;
+        for key in self.keys.iter() {
+            write!(f, "\n{key}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for WalletPolicy {
+    type Err = WalletPolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().map(str::trim).filter(|l| !l.is_empty());
+        let template = lines.next().ok_or(WalletPolicyError::MissingKeys)?;
+        let keys: Vec<_> = lines.map(ToString::to_string).collect();
+        if keys.is_empty() {
+            return Err(WalletPolicyError::MissingKeys);
+        }
+        Ok(WalletPolicy {
+            template: template.to_string(),
+            keys,
+        })
+    }
+}
+
+impl TryFrom<&Descriptor> for WalletPolicy {
+    type Error = WalletPolicyError;
+
+    fn try_from(descriptor: &Descriptor) -> Result<Self, Self::Error> {
+        let desc = descriptor.to_string();
+        let mut template = without_checksum(&desc).to_string();
+
+        let mut placeholders = vec![];
+        let mut error = None;
+        descriptor.for_each_key(|key| {
+            match placeholder(key) {
+                Ok(p) => placeholders.push(p),
+                Err(e) => error = Some(e),
+            }
+            true
+        });
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        // keys are numbered in order of first appearance in the descriptor
+        placeholders.sort_by_key(|(key, _, _)| template.find(key.as_str()));
+        let mut keys: Vec<String> = vec![];
+        for (key, info, suffix) in placeholders {
+            let index = match keys.iter().position(|k| *k == info) {
+                Some(index) => index,
+                None => {
+                    keys.push(info);
+                    keys.len() - 1
+                }
+            };
+            template = template.replace(&key, &format!("@{index}{suffix}"));
+        }
+
+        Ok(WalletPolicy { template, keys })
+    }
+}
+
+impl TryFrom<&WalletPolicy> for Descriptor {
+    type Error = WalletPolicyError;
+
+    fn try_from(policy: &WalletPolicy) -> Result<Self, Self::Error> {
+        let mut desc = policy.template.clone();
+
+        for (index, key) in policy.keys.iter().enumerate() {
+            desc = desc
+                .replace(&format!("@{index}/**"), &format!("{key}/<0;1>/*"))
+                .replace(&format!("@{index}/"), &format!("{key}/"));
+        }
+        if let Some(index) = desc.find('@') {
+            let index = desc[index + 1..]
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>()
+                .parse()
+                .unwrap_or(0);
+            return Err(WalletPolicyError::MissingKey(index));
+        }
+
+        Ok(Descriptor::from_str(&desc)?)
+    }
+}
+
+/// Returns the key as in the descriptor, the key information and the placeholder suffix
+fn placeholder(key: &DescriptorPublicKey) -> Result<(String, String, String), WalletPolicyError> {
+    let key_str = key.to_string();
+    let unsupported = || WalletPolicyError::UnsupportedKey(key_str.clone());
+    let DescriptorPublicKey::MultiXPub(DescriptorMultiXKey {
+        derivation_paths,
+        wildcard: Wildcard::Unhardened,
+        ..
+    }) = key
+    else {
+        return Err(unsupported());
+    };
+    let steps: Vec<u32> = derivation_paths
+        .paths()
+        .iter()
+        .map(|path| match path.as_ref() {
+            [ChildNumber::Normal { index }] => Some(*index),
+            _ => None,
+        })
+        .collect::<Option<_>>()
+        .ok_or_else(unsupported)?;
+    let [receive, change] = steps[..] else {
+        return Err(unsupported());
+    };
+
+    let derivation = format!("/<{receive};{change}>/*");
+    let info = key_str
+        .strip_suffix(&derivation)
+        .ok_or_else(unsupported)?
+        .to_string();
+    let suffix = if (receive, change) == (0, 1) {
+        "/**".to_string()
+    } else {
+        derivation
+    };
+    Ok((key_str, info, suffix))
+}
+
+/// Convert the descriptor to the wallet policy or, if `to_descriptor`, the wallet policy to the
+/// descriptor
+pub fn wallet_policy(input: &str, to_descriptor: bool) -> Result<String, WalletPolicyError> {
+    Ok(if to_descriptor {
+        let policy = WalletPolicy::from_str(input)?;
+        Descriptor::try_from(&policy)?.to_string()
+    } else {
+        let descriptor = Descriptor::from_str(input.trim())?;
+        WalletPolicy::try_from(&descriptor)?.to_string()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::{WalletPolicy, WalletPolicyError};
+    use crate::Descriptor;
+
+    const OWNER: &str = "[01e0b4da/86'/1'/0']tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X";
+    const HEIR: &str = "[8335dcdb/48'/1'/0'/2']tpubDFMWwgXwDVet5E1HvX6h9m32ggTVefxLv7cCjCcEUYsZXqdroHmtMVzzE9RcbwgWa5rCXnZqFXxtKvH7JB5JkTgsNdYdgc1nWJFXHj26ux1";
+
+    #[test]
+    fn test_wallet_policy() {
+        let desc = format!("tr({OWNER}/<0;1>/*,and_v(v:pk({HEIR}/<0;1>/*),older(1000)))");
+        let desc = Descriptor::from_str(&desc).unwrap();
+
+        let policy = WalletPolicy::try_from(&desc).unwrap();
+        assert_eq!(policy.template, "tr(@0/**,and_v(v:pk(@1/**),older(1000)))");
+        assert_eq!(policy.keys, vec![OWNER.to_string(), HEIR.to_string()]);
+        assert_eq!(Descriptor::try_from(&policy).unwrap(), desc);
+
+        let text = policy.to_string();
+        assert_eq!(WalletPolicy::from_str(&text).unwrap(), policy);
+
+        // same key with different derivations
+        let desc =
+            format!("wsh(or_d(pk({OWNER}/<0;1>/*),and_v(v:pkh({OWNER}/<2;3>/*),older(10))))");
+        let desc = Descriptor::from_str(&desc).unwrap();
+        let policy = WalletPolicy::try_from(&desc).unwrap();
+        assert_eq!(
+            policy.template,
+            "wsh(or_d(pk(@0/**),and_v(v:pkh(@0/<2;3>/*),older(10))))"
+        );
+        assert_eq!(policy.keys.len(), 1);
+        assert_eq!(Descriptor::try_from(&policy).unwrap(), desc);
+
+        let single = Descriptor::from_str(&format!("tr({OWNER}/0/*)")).unwrap();
+        assert!(matches!(
+            WalletPolicy::try_from(&single),
+            Err(WalletPolicyError::UnsupportedKey(_))
+        ));
+
+        let missing = WalletPolicy {
+            template: "tr(@0/**,pk(@1/**))".to_string(),
+            keys: vec![OWNER.to_string()],
+        };
+        assert!(matches!(
+            Descriptor::try_from(&missing),
+            Err(WalletPolicyError::MissingKey(1))
+        ));
+    }
+}
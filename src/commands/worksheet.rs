@@ -0,0 +1,236 @@
+use std::fmt::Write;
+
+use codex32::{ChecksumEngine, Codex32String, Fe};
+
+use super::{seed_combine, SeedError};
+use crate::error::Codex32ErrorWrapper;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WorksheetError {
+    #[error(transparent)]
+    Seed(#[from] SeedError),
+
+    #[error(transparent)]
+    Codex32(#[from] Codex32ErrorWrapper),
+
+    #[error(transparent)]
+    Fmt(#[from] std::fmt::Error),
+
+    #[error("The string '{0}' doesn't contain the separator '1'")]
+    MissingSeparator(String),
+}
+
+/// Render the worksheets to verify by hand the given Codex32 strings, one checksum table for every
+/// string, even if its checksum is invalid, and, if more than one share is given, the recovery table
+/// to combine them into the secret.
+///
+/// If the shares cannot be combined, for example because they are less than the threshold, the
+/// reason is written in place of the recovery table.
+pub fn codex32_worksheet(strings: &[String]) -> Result<String, WorksheetError> {
+    let strings: Vec<_> = strings.iter().map(|s| s.trim().to_string()).collect();
+
+    let mut result = String::new();
+    for string in strings.iter() {
+        checksum_table(&mut result, string)?;
+        writeln!(result)?;
+    }
+    if strings.len() > 1 {
+        match seed_combine(&strings) {
+            Ok(secret) => recovery_table(&mut result, &strings, &secret)?,
+            Err(e) => writeln!(result, "No recovery worksheet: {e}")?,
+        }
+    }
+
+    Ok(result.trim_end().to_string())
+}
+
+fn to_string(fes: &[Fe]) -> String {
+    fes.iter().map(|fe| fe.to_char()).collect()
+}
+
+/// Returns the human readable part and the data part, lowercase
+fn split(string: &str) -> Result<(String, String), WorksheetError> {
+    let s = string.to_ascii_lowercase();
+    let (hrp, data) = s
+        .rsplit_once('1')
+        .ok_or_else(|| WorksheetError::MissingSeparator(string.to_string()))?;
+    Ok((hrp.to_string(), data.to_string()))
+}
+
+/// For every character of the data part, the residue is shifted left appending the character, the
+/// leftmost character removed is multiplied by the generator and added to obtain the new residue.
+/// The string is valid if the final residue is `secretshare32`.
+fn checksum_table(out: &mut String, share: &str) -> Result<(), WorksheetError> {
+    let (hrp, data) = split(share)?;
+    let mut engine = if share.len() < 94 {
+        ChecksumEngine::new_codex32_short()
+    } else {
+        ChecksumEngine::new_codex32_long()
+    };
+    engine.input_hrp(&hrp).map_err(Codex32ErrorWrapper)?;
+    let mut residue = engine.clone().into_residue();
+    let width = residue.len();
+
+    writeln!(out, "Checksum worksheet of {share}")?;
+    writeln!(
+        out,
+        "initial residue of hrp '{hrp}': {}",
+        to_string(&residue)
+    )?;
+    let header = format!(
+        "{:>3} {:>2} {:>4} {:<width$}   {:<width$}   {:<width$}",
+        "#", "in", "lead", "shifted", "lead×gen", "residue"
+    );
+    writeln!(out, "{}", header.trim_end())?;
+    for (i, c) in data.chars().enumerate() {
+        let input = Fe::from_char(c).map_err(Codex32ErrorWrapper)?;
+        let leading = residue[0];
+        let mut shifted = residue[1..].to_vec();
+        shifted.push(input);
+        engine.input_fe(input);
+        residue = engine.clone().into_residue();
+        let product: Vec<_> = shifted
+            .iter()
+            .zip(residue.iter())
+            .map(|(s, r)| *s + *r)
+            .collect();
+        writeln!(
+            out,
+            "{:>3} {:>2} {:>4} {} + {} = {}",
+            i + 1,
+            c,
+            leading.to_char(),
+            to_string(&shifted),
+            to_string(&product),
+            to_string(&residue)
+        )?;
+    }
+    let valid = if engine.is_valid() {
+        "valid"
+    } else {
+        "INVALID"
+    };
+    writeln!(out, "final residue: {} ({valid})", to_string(&residue))?;
+
+    Ok(())
+}
+
+/// Every share is multiplied character by character by its Lagrange coefficient at the index `s`,
+/// the sum of the products is the secret.
+fn recovery_table(
+    out: &mut String,
+    shares: &[String],
+    secret: &Codex32String,
+) -> Result<(), WorksheetError> {
+    let (hrp, _) = split(&secret.to_string())?;
+
+    let parsed: Vec<(Fe, Vec<Fe>)> = shares
+        .iter()
+        .map(|share| {
+            let (_, data) = split(share)?;
+            let fes = data
+                .chars()
+                .map(Fe::from_char)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(Codex32ErrorWrapper)?;
+            Ok((fes[5], fes))
+        })
+        .collect::<Result<_, WorksheetError>>()?;
+    let indexes: Vec<char> = parsed.iter().map(|(i, _)| i.to_char()).collect();
+
+    let prefix = format!("{hrp}1");
+    let pad = " ".repeat(prefix.len());
+    writeln!(
+        out,
+        "Recovery worksheet of the secret from shares {}",
+        indexes
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )?;
+    let mut sum = vec![Fe::Q; parsed[0].1.len()];
+    for (index, data) in parsed.iter() {
+        let multiplier = lagrange_at_s(*index, parsed.iter().map(|(i, _)| *i));
+        let product: Vec<_> = data.iter().map(|fe| *fe * multiplier).collect();
+        for (s, p) in sum.iter_mut().zip(product.iter()) {
+            *s += *p;
+        }
+        writeln!(
+            out,
+            "share {}: {prefix}{} × {}",
+            index.to_char(),
+            to_string(data),
+            multiplier.to_char()
+        )?;
+        writeln!(out, "         {pad}{}", to_string(&product))?;
+    }
+    writeln!(out, "secret:  {prefix}{}", to_string(&sum))?;
+
+    debug_assert!(format!("{prefix}{}", to_string(&sum)).eq_ignore_ascii_case(&secret.to_string()));
+
+    Ok(())
+}
+
+/// The Lagrange coefficient of the share at `index` to interpolate at `s`
+fn lagrange_at_s(index: Fe, indexes: impl Iterator<Item = Fe>) -> Fe {
+    indexes
+        .filter(|i| *i != index)
+        .fold(Fe::P, |acc, other| acc * (Fe::S + other) / (index + other))
+}
+
+#[cfg(test)]
+mod test {
+    use codex32::Fe;
+
+    use crate::commands::{seed_combine, seed_shares, EntropySource};
+
+    const DICES: &str = "43242535241352135351234134123421351351342134123412351341324134134213512512353513123423423433222413233";
+    const CODEX_32: &str =
+        "ms10leetst9q78hvegp0h6xfpc49asgsdaj9kpya2jkr9pfehf6awv43ep4sqjf0ucdd53raxd";
+
+    #[test]
+    fn checksum_table() {
+        let worksheet = super::codex32_worksheet(&[CODEX_32.to_string()]).unwrap();
+        let lines: Vec<_> = worksheet.lines().collect();
+        assert_eq!(lines.len(), 3 + 71 + 1);
+        assert_eq!(
+            lines[3],
+            "  1  0    q qqqqqqprrqdn0 + qqqqqqqqqqqqq = qqqqqqprrqdn0"
+        );
+        assert!(lines[74].starts_with("final residue: secretshare32 (valid)"));
+
+        let mistyped = CODEX_32.replacen("st9q", "sx9p", 1);
+        let worksheet = super::codex32_worksheet(&[mistyped]).unwrap();
+        assert!(worksheet.ends_with("(INVALID)"));
+
+        super::codex32_worksheet(&["msleet".to_string()]).unwrap_err();
+    }
+
+    #[test]
+    fn recovery_table() {
+        let shares: Vec<_> = seed_shares(DICES, EntropySource::Dice, "leet", 3, 5, None)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        let selected = [shares[4].clone(), shares[1].clone(), shares[2].clone()];
+        let worksheet = super::codex32_worksheet(&selected).unwrap();
+        let secret = seed_combine(&selected).unwrap();
+        assert!(worksheet.ends_with(&format!("secret:  {secret}")));
+        assert_eq!(worksheet.matches("(valid)").count(), 3);
+
+        let uppercase: Vec<_> = selected.iter().map(|s| s.to_ascii_uppercase()).collect();
+        let worksheet = super::codex32_worksheet(&uppercase).unwrap();
+        assert!(worksheet.ends_with(&format!("secret:  {secret}")));
+
+        let worksheet = super::codex32_worksheet(&selected[..2]).unwrap();
+        assert_eq!(worksheet.matches("(valid)").count(), 2);
+        assert!(worksheet.ends_with("No recovery worksheet: Not enough Codex32 shares to recover the secret: threshold is 3, given 2"));
+
+        let indexes = [Fe::A, Fe::C];
+        let a = super::lagrange_at_s(Fe::A, indexes.iter().copied());
+        let c = super::lagrange_at_s(Fe::C, indexes.iter().copied());
+        assert_eq!(a + c, Fe::P);
+    }
+}
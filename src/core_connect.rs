@@ -1,12 +1,13 @@
 use std::{
     net::{Ipv4Addr, SocketAddrV4},
     path::PathBuf,
+    time::Duration,
 };
 
 use anyhow::Context;
 use bitcoin::Network;
 use bitcoind::{
-    bitcoincore_rpc::{self, Auth, Client, RpcApi},
+    bitcoincore_rpc::{self, jsonrpc, Auth, Client, RpcApi},
     BitcoinD, ConnectParams,
 };
 
@@ -42,8 +43,30 @@ impl CoreConnect {
         self.client_inner(&url)
     }
 
+    /// Like [`CoreConnect::client_with_wallet`] but requests abort after `timeout` instead of the
+    /// default 15 seconds, needed by calls like `importdescriptors` that could rescan the chain
+    pub(crate) fn client_with_wallet_timeout(
+        &self,
+        wallet_name: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<Client> {
+        let url = format!("http://{}/wallet/{}", self.node_socket, wallet_name);
+        let (user, pass) = Auth::CookieFile(self.node_cookie_path.clone()).get_user_pass()?;
+        let transport = jsonrpc::simple_http::Builder::new()
+            .url(&url)
+            .with_context(|| format!("Invalid rpc url {url}"))?
+            .auth(user.unwrap_or_default(), pass)
+            .timeout(timeout)
+            .build();
+        let client = Client::from_jsonrpc(jsonrpc::Client::with_transport(transport));
+        Self::check_network(&client, self.network)
+            .with_context(|| format!("Connecting to core RPC on {}", url))?;
+
+        Ok(client)
+    }
+
     fn client_inner(&self, url: &str) -> anyhow::Result<Client> {
-        let client = Client::new(&url, Auth::CookieFile(self.node_cookie_path.clone()))
+        let client = Client::new(url, Auth::CookieFile(self.node_cookie_path.clone()))
             .with_context(|| {
                 format!(
                     "Creating an rpc client to {} with cookie file {:?}",
@@ -25,6 +25,24 @@ pub enum Error {
     #[error(transparent)]
     Broadcast(#[from] commands::BroadcastError),
 
+    #[error(transparent)]
+    AccountXpub(#[from] commands::AccountXpubError),
+
+    #[error(transparent)]
+    Addresses(#[from] commands::AddressesError),
+
+    #[error(transparent)]
+    Bip85(#[from] commands::Bip85Error),
+
+    #[error(transparent)]
+    Worksheet(#[from] commands::WorksheetError),
+
+    #[error(transparent)]
+    Encrypt(#[from] commands::EncryptError),
+
+    #[error(transparent)]
+    PsbtsAge(#[from] crate::psbts_age::PsbtsAgeError),
+
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
@@ -49,6 +67,15 @@ pub enum Error {
     #[error(transparent)]
     PsbtDecodeError(#[from] psbts_serde::DecodeError),
 
+    #[error(transparent)]
+    Export(#[from] commands::ExportError),
+
+    #[error(transparent)]
+    WalletPolicy(#[from] commands::WalletPolicyError),
+
+    #[error(transparent)]
+    VerifyAddress(#[from] commands::VerifyAddressError),
+
     #[error(transparent)]
     Balance(#[from] commands::BalanceError),
 
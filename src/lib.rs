@@ -5,7 +5,7 @@ use anyhow::Context;
 use bitcoin::Network;
 use clap::{CommandFactory, Parser};
 use clap_complete::generate;
-use commands::{Commands, CoreConnectOptional, Seed};
+use commands::{Commands, CoreConnectOptional, PassphraseOptions, Seed};
 use error::Error;
 use std::{fs, io::Read, str::FromStr};
 use stdin::StdinData;
@@ -16,6 +16,7 @@ pub mod client_ext;
 pub mod commands;
 pub mod core_connect;
 pub mod error;
+pub mod psbts_age;
 pub mod psbts_serde;
 pub mod stdin;
 pub mod stdout;
@@ -40,20 +41,74 @@ pub struct Cli {
 
 pub fn inner_main(cli: Cli, stdin: Option<StdinData>) -> anyhow::Result<Vec<u8>> {
     Ok(match cli.command {
-        Commands::Seed { codex32_id } => {
-            let dices = stdin.ok_or(Error::StdinExpected)?.to_single_text_line()?;
+        Commands::Seed {
+            codex32_id,
+            entropy,
+            force,
+            mix,
+            final_word,
+            threshold,
+            shares,
+        } => {
+            let sequence = stdin.ok_or(Error::StdinExpected)?.to_single_text_line()?;
+
+            if final_word {
+                let words = commands::final_words(&sequence)?;
+                return Ok(words.join("\n").as_bytes().to_vec());
+            }
+
+            let report = entropy.report(&sequence)?;
+            log::info!("entropy report\n{report}");
+            if !force {
+                report.check()?;
+            }
 
-            commands::seed(&dices, codex32_id)?
+            let random = mix.then(commands::system_random);
+            let random = random.as_ref();
+
+            // clap requires `--codex32-id` and `--shares` with `--threshold`
+            match (threshold, shares, codex32_id) {
+                (Some(threshold), Some(shares), Some(codex32_id)) => {
+                    let id = &codex32_id;
+                    commands::seed_shares(&sequence, entropy, id, threshold, shares, random)?
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                        .as_bytes()
+                        .to_vec()
+                }
+                (_, _, codex32_id) => commands::seed(&sequence, entropy, codex32_id, random)?
+                    .to_string()
+                    .as_bytes()
+                    .to_vec(),
+            }
+        }
+
+        Commands::SeedCombine => {
+            let shares = stdin.ok_or(Error::StdinExpected)?.to_multiline_string()?;
+
+            commands::seed_combine(&shares)?
                 .to_string()
                 .as_bytes()
                 .to_vec()
         }
 
-        Commands::Descriptor { public, account } => {
-            let key = stdin.ok_or(Error::StdinExpected)?.to_single_text_line()?;
-            let seed = Seed::from_str(&key)?;
+        Commands::Codex32Worksheet => {
+            let strings = stdin.ok_or(Error::StdinExpected)?.to_multiline_string()?;
+
+            commands::codex32_worksheet(&strings)?.as_bytes().to_vec()
+        }
+
+        Commands::Descriptor {
+            public,
+            account,
+            options,
+            passphrase,
+        } => {
+            let seed = read_seed(stdin, &passphrase)?;
 
-            commands::descriptor(seed, cli.network, account, public)?
+            commands::descriptor(seed, cli.network, account, public, &options.template()?)?
                 .to_string()
                 .as_bytes()
                 .to_vec()
@@ -61,11 +116,11 @@ pub fn inner_main(cli: Cli, stdin: Option<StdinData>) -> anyhow::Result<Vec<u8>>
 
         Commands::Import {
             wallet_name,
-            with_private_keys,
+            options,
         } => {
             let descriptor = stdin.ok_or(Error::StdinExpected)?.to_single_text_line()?;
             let core_connect = CoreConnect::try_from((cli.core_connect, cli.network))?;
-            commands::import(&core_connect, &descriptor, &wallet_name, with_private_keys)?
+            commands::import(&core_connect, &descriptor, &wallet_name, &options)?
                 .as_bytes()
                 .to_vec()
         }
@@ -81,6 +136,7 @@ pub fn inner_main(cli: Cli, stdin: Option<StdinData>) -> anyhow::Result<Vec<u8>>
             from_wallet_name,
             to_wallet_name,
             locktime_future,
+            encrypt,
         } => {
             let core_connect = CoreConnect::try_from((cli.core_connect, cli.network))?;
             let psbts = commands::locktime(
@@ -89,11 +145,12 @@ pub fn inner_main(cli: Cli, stdin: Option<StdinData>) -> anyhow::Result<Vec<u8>>
                 &to_wallet_name,
                 locktime_future,
             )?;
-            psbts_serde::serialize(&psbts)
+            encrypt.serialize(&psbts)?
         }
         Commands::Sign {
             wallet_name,
             psbt_file,
+            encrypt,
         } => {
             let descriptor = stdin.ok_or(Error::StdinExpected)?.to_single_text_line()?;
             let mut file_content = vec![];
@@ -108,7 +165,7 @@ pub fn inner_main(cli: Cli, stdin: Option<StdinData>) -> anyhow::Result<Vec<u8>>
             let signed_psbts: Vec<_> =
                 commands::sign(&core_connect, &descriptor, &wallet_name, &psbts)?;
 
-            psbts_serde::serialize(&signed_psbts)
+            encrypt.serialize(&signed_psbts)?
         }
 
         Commands::Broadcast => {
@@ -121,12 +178,105 @@ pub fn inner_main(cli: Cli, stdin: Option<StdinData>) -> anyhow::Result<Vec<u8>>
                 .to_vec()
         }
 
-        Commands::Identity { private } => {
-            let key = stdin.ok_or(Error::StdinExpected)?.to_single_text_line()?;
-            let seed = Seed::from_str(&key)?;
+        Commands::Fingerprint { passphrase } => {
+            let seed = read_seed(stdin, &passphrase)?;
+
+            seed.fingerprint()?.to_string().as_bytes().to_vec()
+        }
+
+        Commands::Encrypt { recipient, armor } => {
+            let plaintext = stdin.ok_or(Error::StdinExpected)?.to_vec();
+
+            commands::encrypt(&plaintext, &recipient, armor)?
+        }
+
+        Commands::Decrypt {
+            file,
+            passphrase,
+            identity,
+        } => {
+            let (secret, passphrase) = read_secret(stdin, &passphrase)?;
+            let kdf_passphrase = identity.kdf_passphrase()?;
+            let identity = commands::to_identity(
+                &secret,
+                &passphrase,
+                &identity.path(),
+                kdf_passphrase.as_deref(),
+            )?;
+            let ciphertext = fs::read(&file).with_context(|| format!("cannot read {:?}", file))?;
+
+            commands::decrypt(&ciphertext, &identity)?
+        }
+
+        Commands::AccountXpub {
+            path,
+            format,
+            uppercase,
+            passphrase,
+        } => {
+            let seed = read_seed(stdin, &passphrase)?;
+
+            if uppercase {
+                commands::account_xpub_ur(&seed, cli.network, &path)?
+            } else {
+                commands::account_xpub(&seed, cli.network, &path, format)?
+            }
+            .as_bytes()
+            .to_vec()
+        }
 
-            let identity = commands::identity(&seed)?;
-            if private {
+        Commands::Bip85 {
+            application,
+            words,
+            length,
+            index,
+            passphrase,
+        } => {
+            let seed = read_seed(stdin, &passphrase)?;
+
+            commands::bip85(&seed, cli.network, application, words, length, index)?
+                .as_bytes()
+                .to_vec()
+        }
+
+        Commands::VerifyBackup {
+            expected,
+            passphrase,
+        } => {
+            let (backup, passphrase) = read_secret(stdin, &passphrase)?;
+
+            commands::verify_backup(&backup, &passphrase, &expected, cli.network)?
+                .as_bytes()
+                .to_vec()
+        }
+
+        Commands::Identity {
+            private,
+            split,
+            combine,
+            passphrase,
+            identity,
+        } => {
+            let identity = if combine {
+                let shares = stdin.ok_or(Error::StdinExpected)?.to_multiline_string()?;
+                commands::identity_combine(&shares)?
+            } else {
+                let seed = read_seed(stdin, &passphrase)?;
+                match identity.kdf_passphrase()? {
+                    Some(kdf) => commands::hardened_identity(&seed, &identity.path(), &kdf)?,
+                    None => commands::identity(&seed, &identity.path())?,
+                }
+            };
+
+            if let Some(split) = split {
+                commands::identity_shares(&identity, split)?
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .as_bytes()
+                    .to_vec()
+            } else if private {
                 identity
                     .to_string()
                     .expose_secret()
@@ -171,6 +321,39 @@ pub fn inner_main(cli: Cli, stdin: Option<StdinData>) -> anyhow::Result<Vec<u8>>
                 .as_bytes()
                 .to_vec()
         }
+        Commands::Addresses {
+            descriptor,
+            change,
+            start,
+            count,
+        } => commands::addresses(&descriptor, cli.network, change, start, count)?
+            .as_bytes()
+            .to_vec(),
+
+        Commands::Export {
+            format,
+            descriptor,
+            label,
+        } => commands::export(&descriptor, cli.network, format, &label)?
+            .as_bytes()
+            .to_vec(),
+
+        Commands::WalletPolicy { to_descriptor } => {
+            let input = stdin.ok_or(Error::StdinExpected)?.to_string()?;
+
+            commands::wallet_policy(&input, to_descriptor)?
+                .as_bytes()
+                .to_vec()
+        }
+
+        Commands::VerifyAddress {
+            address,
+            descriptor,
+            gap,
+        } => commands::verify_address(&address, &descriptor, cli.network, gap)?
+            .as_bytes()
+            .to_vec(),
+
         Commands::Details { descriptor } => {
             let psbts = stdin.ok_or(Error::StdinExpected)?.to_psbts()?;
 
@@ -180,3 +363,33 @@ pub fn inner_main(cli: Cli, stdin: Option<StdinData>) -> anyhow::Result<Vec<u8>>
         }
     })
 }
+
+/// Read the seed from stdin, with the optional bip39 passphrase from the last stdin line or a file
+fn read_seed(stdin: Option<StdinData>, options: &PassphraseOptions) -> anyhow::Result<Seed> {
+    let (secret, passphrase) = read_secret(stdin, options)?;
+    let seed = Seed::from_str(&secret)?;
+
+    Ok(seed.with_passphrase(&passphrase))
+}
+
+/// Returns the secret lines of the stdin and the bip39 passphrase
+fn read_secret(
+    stdin: Option<StdinData>,
+    options: &PassphraseOptions,
+) -> anyhow::Result<(String, String)> {
+    let stdin = stdin.ok_or(Error::StdinExpected)?;
+    if options.passphrase {
+        let (lines, passphrase) = stdin.to_secret_and_last_line()?;
+        return Ok((lines.join("\n"), passphrase));
+    }
+    let lines = stdin.to_multiline_string()?;
+    let passphrase = if let Some(path) = options.passphrase_file.as_ref() {
+        let content =
+            fs::read_to_string(path).with_context(|| format!("cannot read {:?}", path))?;
+        content.strip_suffix('\n').unwrap_or(&content).to_string()
+    } else {
+        String::new()
+    };
+
+    Ok((lines.join("\n"), passphrase))
+}
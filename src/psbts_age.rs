@@ -0,0 +1,67 @@
+//! A list of PSBTs serialized with [`psbts_serde`] and encrypted with age to many recipients, every
+//! recipient could decrypt it independently, for example with `dinasty decrypt`.
+
+use age::x25519::{Identity, Recipient};
+use bitcoin::psbt::PartiallySignedTransaction;
+
+use crate::{
+    commands::{self, EncryptError},
+    psbts_serde::{self, DecodeError},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum PsbtsAgeError {
+    #[error(transparent)]
+    Encrypt(#[from] EncryptError),
+
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+/// Serialize the PSBTs and encrypt them to every recipient, ascii armored if `armor`
+pub fn encrypt(
+    psbts: &[PartiallySignedTransaction],
+    recipients: &[Recipient],
+    armor: bool,
+) -> Result<Vec<u8>, PsbtsAgeError> {
+    Ok(commands::encrypt(
+        &psbts_serde::serialize(psbts),
+        recipients,
+        armor,
+    )?)
+}
+
+/// Decrypt with the identity of one of the recipients and deserialize the PSBTs
+pub fn decrypt(
+    bytes: &[u8],
+    identity: &Identity,
+) -> Result<Vec<PartiallySignedTransaction>, PsbtsAgeError> {
+    Ok(psbts_serde::deserialize(&commands::decrypt(
+        bytes, identity,
+    )?)?)
+}
+
+#[cfg(test)]
+mod test {
+    use age::x25519::Identity;
+    use bitcoin::psbt::PartiallySignedTransaction;
+
+    use crate::test_util::psbt_base64;
+
+    #[test]
+    fn psbts_age_roundtrip() {
+        let psbt: PartiallySignedTransaction = psbt_base64().parse().unwrap();
+        let psbts = vec![psbt.clone(), psbt];
+        let heir = Identity::generate();
+        let lawyer = Identity::generate();
+        let other = Identity::generate();
+
+        for armor in [true, false] {
+            let encrypted =
+                super::encrypt(&psbts, &[heir.to_public(), lawyer.to_public()], armor).unwrap();
+            assert_eq!(super::decrypt(&encrypted, &heir).unwrap(), psbts);
+            assert_eq!(super::decrypt(&encrypted, &lawyer).unwrap(), psbts);
+            assert!(super::decrypt(&encrypted, &other).is_err());
+        }
+    }
+}
@@ -14,6 +14,9 @@ pub enum StdinError {
     #[error("One text line expected in stdin, found {0}")]
     Not1Lines(usize),
 
+    #[error("The secret is expected in stdin before the passphrase line")]
+    SecretExpected,
+
     #[error(transparent)]
     DecodeError(#[from] psbts_serde::DecodeError),
 }
@@ -40,11 +43,28 @@ impl StdinData {
     pub fn to_multiline_string(self) -> Result<Vec<String>, StdinError> {
         let string = self.to_string()?;
         Ok(string
-            .split("\n")
+            .split('\n')
             .map(ToString::to_string)
             .filter(|s| !s.is_empty())
             .collect())
     }
+
+    /// Split the stdin in the non-empty secret lines and the last line, which is kept even if
+    /// empty because an empty passphrase is valid. A single trailing newline ends the last line.
+    pub fn to_secret_and_last_line(self) -> Result<(Vec<String>, String), StdinError> {
+        let string = self.to_string()?;
+        let string = string.strip_suffix('\n').unwrap_or(&string);
+        let (secret, last) = string.rsplit_once('\n').ok_or(StdinError::SecretExpected)?;
+        let secret: Vec<_> = secret
+            .split('\n')
+            .filter(|s| !s.is_empty())
+            .map(ToString::to_string)
+            .collect();
+        if secret.is_empty() {
+            return Err(StdinError::SecretExpected);
+        }
+        Ok((secret, last.to_string()))
+    }
     pub fn to_vec(self) -> Vec<u8> {
         self.0
     }
@@ -65,11 +85,14 @@ pub fn read_stdin() -> StdinData {
 
 impl Commands {
     pub fn needs_stdin(&self) -> bool {
-        match self {
+        !matches!(
+            self,
             Commands::Locktime { .. }
-            | Commands::Refresh { .. }
-            | Commands::GenerateCompletion { .. } => false,
-            _ => true,
-        }
+                | Commands::Refresh { .. }
+                | Commands::GenerateCompletion { .. }
+                | Commands::Addresses { .. }
+                | Commands::VerifyAddress { .. }
+                | Commands::Export { .. }
+        )
     }
 }
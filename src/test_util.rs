@@ -23,6 +23,15 @@ pub fn sh<T: AsRef<[u8]>>(stdin: T, command: &str) -> StdoutData {
     StdoutData::new(bytes)
 }
 
+/// Like [`sh`] but the command is expected to fail, returns the error message
+pub fn sh_err<T: AsRef<[u8]>>(stdin: T, command: &str) -> String {
+    let stdin = (!stdin.as_ref().is_empty()).then(|| StdinData::new(stdin.as_ref().to_vec()));
+
+    let cli = Cli::try_parse_from(command.split(' ')).unwrap();
+
+    inner_main(cli, stdin).unwrap_err().to_string()
+}
+
 pub struct TestNode {
     pub node: BitcoinD,
     pub node_address: Address,
@@ -66,13 +75,22 @@ fn setup_wallets(node: &BitcoinD) -> TestWallets {
     let core_connect: CoreConnect = (node, Network::Regtest).into();
 
     let desc = "tr([01e0b4da/86h/1h/0h]tprv8fXspLN8b22B19ViogBWdGHR4ZHkoUd7VvMpoUZCkPZtHiKLZyc9H9pgfTnZwrosXQ5hKLTdSCPerVrgtewQjTSRy1YEngEZXHNCvTodhtz/<0;1>/*)";
-    commands::import(&core_connect, desc, "signer", true).unwrap();
+    commands::import(
+        &core_connect,
+        desc,
+        "signer",
+        &commands::ImportOptions {
+            with_private_keys: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
 
     let desc = "tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)";
-    commands::import(&core_connect, desc, "watch_only", false).unwrap();
+    commands::import(&core_connect, desc, "watch_only", &Default::default()).unwrap();
 
     let desc = "tr([01e0b4da/86h/1h/1h]tpubDCDuxkQNjPhqtq5ARHKc6t5QPg8CUyqJ6uzVkLqDBQtJ47Fac1JwrMUN9Zr6c3dAD5bGxL3DihfZUisSuszupSLoanydKxT8giNcVJSo2vq/<0;1>/*)";
-    commands::import(&core_connect, desc, "heir_watch_only", false).unwrap();
+    commands::import(&core_connect, desc, "heir_watch_only", &Default::default()).unwrap();
 
     let signer = core_connect.client_with_wallet("signer").unwrap();
     let watch_only = core_connect.client_with_wallet("watch_only").unwrap();
@@ -97,7 +115,7 @@ fn setup_wallets(node: &BitcoinD) -> TestWallets {
 
     node.client.generate_to_address(101, &address).unwrap();
 
-    signer.wallet_lock();
+    signer.wallet_lock().unwrap();
 
     TestWallets {
         signer,
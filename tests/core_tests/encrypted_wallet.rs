@@ -2,7 +2,7 @@ use crate::{create_random_extended, setup};
 use bitcoin::{Amount, Network};
 use bitcoind::bitcoincore_rpc::RpcApi;
 use dinasty::client_ext::ClientExt;
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 /// Test a wallet with keys encrypted with a passphrase
 #[test]
@@ -20,10 +20,12 @@ fn encrypted_wallet() {
         .client
         .create_blank_wallet("encrypted", &core_connect, false, Some(passphrase))
         .unwrap();
-    client.wallet_passphrase(passphrase);
+    client
+        .wallet_passphrase(passphrase, Duration::from_secs(10))
+        .unwrap();
     client.import_descriptor(&desc, false).unwrap();
     client.import_descriptor(&desc_change, true).unwrap();
-    client.wallet_lock();
+    client.wallet_lock().unwrap();
     let first = client.get_new_bech32m_address(Network::Regtest).unwrap();
 
     node.client.generate_to_address(1, &first).unwrap();
@@ -40,7 +42,9 @@ fn encrypted_wallet() {
     assert!(format!("{error:?}")
         .contains("Error: Please enter the wallet passphrase with walletpassphrase first."));
 
-    client.wallet_passphrase(passphrase);
+    client
+        .wallet_passphrase(passphrase, Duration::from_secs(10))
+        .unwrap();
 
     let psbt = client
         .wallet_process_psbt(&psbt.psbt, None, None, None)
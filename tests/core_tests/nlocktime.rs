@@ -75,8 +75,10 @@ fn nlocktime() {
     let mut outputs = HashMap::new();
     outputs.insert(node_address.to_string(), unspent.amount);
 
-    let mut options = WalletCreateFundedPsbtOptions::default();
-    options.subtract_fee_from_outputs = vec![0];
+    let options = WalletCreateFundedPsbtOptions {
+        subtract_fee_from_outputs: vec![0],
+        ..Default::default()
+    };
     let psbt = alice_wo_client
         .wallet_create_funded_psbt(&[input], &outputs, Some(locktime), Some(options), None)
         .unwrap();
@@ -149,7 +149,11 @@ fn tr_inheritance() {
             assert!(!result[0].allowed);
             assert_eq!(result[0].reject_reason, Some("non-BIP68-final".to_string()));
 
-            if let Ok(_) = node.client.generate_to_address(100, &non_owned_address) {
+            if node
+                .client
+                .generate_to_address(100, &non_owned_address)
+                .is_ok()
+            {
                 remaining -= 100;
             } // make the CSV expire
         }
@@ -1,10 +1,13 @@
 use std::{
     io::Write,
     process::{Output, Stdio},
+    thread,
+    time::{Duration, Instant},
 };
 
 mod full;
 mod help;
+mod no_stdin;
 
 fn dinasty(args: Vec<String>, stdin_string: Option<String>) -> Output {
     let stdin = stdin_string
@@ -29,3 +32,25 @@ fn dinasty(args: Vec<String>, stdin_string: Option<String>) -> Output {
     }
     child.wait_with_output().unwrap()
 }
+
+/// Like [`dinasty`] but stdin is left open, like in an interactive shell, so that the command
+/// fails if it waits for stdin instead of terminating within `timeout`
+fn dinasty_open_stdin(args: Vec<String>, timeout: Duration) -> Output {
+    let exe = std::env::var("DINASTY_EXE").unwrap_or("./target/debug/dinasty".to_string());
+    let mut child = std::process::Command::new(exe)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let deadline = Instant::now() + timeout;
+    while child.try_wait().unwrap().is_none() {
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("dinasty is still waiting for stdin after {timeout:?}");
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    child.wait_with_output().unwrap()
+}
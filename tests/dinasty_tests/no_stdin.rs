@@ -0,0 +1,33 @@
+use std::{str::from_utf8, time::Duration};
+
+use crate::dinasty_tests::dinasty_open_stdin;
+
+const DESC: &str = "tr([01e0b4da/86h/1h/0h]tpubDCDuxkQNjPhqtcXWhKr72fwXdaogxop25Dxc5zbWAfNH8Ca7CNRjTeSYqZVA87gW4e8MY9ZcgNCMYrBLyGSRzrCJfEwh6ekK81A2KQPwn4X/<0;1>/*)";
+
+fn run(args: &[&str]) -> String {
+    let mut all = vec!["--network", "regtest"];
+    all.extend_from_slice(args);
+    let args = all.iter().map(ToString::to_string).collect();
+    let output = dinasty_open_stdin(args, Duration::from_secs(10));
+    assert!(output.status.success());
+    from_utf8(&output.stdout).unwrap().to_string()
+}
+
+#[test]
+fn addresses() {
+    let stdout = run(&["addresses", "--descriptor", DESC, "--count", "1"]);
+    assert!(stdout.starts_with("bcrt1pccadr74cd29xf5y0eax2dwnfvjeqwa65c9h09f7cw6c2h6c7rjyswwdgx4"));
+}
+
+#[test]
+fn verify_address() {
+    let address = "bcrt1pccadr74cd29xf5y0eax2dwnfvjeqwa65c9h09f7cw6c2h6c7rjyswwdgx4";
+    let stdout = run(&["verify-address", address, "--descriptor", DESC]);
+    assert!(stdout.starts_with("ok"));
+}
+
+#[test]
+fn export() {
+    let stdout = run(&["export", "bsms", "--descriptor", DESC]);
+    assert!(stdout.starts_with("BSMS 1.0"));
+}